@@ -1,4 +1,4 @@
-use super::{output, spi};
+use super::{bus, input, output, spi};
 
 /// Create a mock device
 #[derive(Debug)]
@@ -9,9 +9,17 @@ impl Mock {
         output::mock::MockBuilder::new(name)
     }
 
+    pub fn input_pin(name: &str) -> input::mock::MockBuilder {
+        input::mock::MockBuilder::new(name)
+    }
+
     pub fn spi(name: &str) -> spi::mock::MockBuilder {
         spi::mock::MockBuilder::new(name)
     }
+
+    pub fn bus(name: &str) -> bus::MockBusBuilder {
+        bus::MockBusBuilder::new(name)
+    }
 }
 
 /// Create a device intercept
@@ -23,6 +31,10 @@ impl Intercept {
         output::intercept::InterceptBuilder::new(name)
     }
 
+    pub fn input_pin(name: &str) -> input::intercept::InterceptBuilder {
+        input::intercept::InterceptBuilder::new(name)
+    }
+
     pub fn spi(name: &str) -> spi::intercept::InterceptBuilder {
         spi::intercept::InterceptBuilder::new(name)
     }
@@ -36,6 +48,7 @@ macro_rules! builder {
         pub struct $builder {
             name: String,
             opts: $opts,
+            sink: Option<$crate::dev::sink::SharedSink>,
             $($field: $type),*
         }
 
@@ -44,6 +57,7 @@ macro_rules! builder {
                 Self {
                     name: name.to_owned(),
                     opts: <$opts>::new(),
+                    sink: None,
                     $($field: $val),*
                 }
             }
@@ -54,6 +68,13 @@ macro_rules! builder {
                 self
             }
 
+            /// Send events to the sink instead of stdout. Pass a clone of
+            /// a shared sink to several devices to interleave their events.
+            pub fn with_sink<L: $crate::dev::sink::LogSink + 'static>(mut self, sink: L) -> Self {
+                self.sink = Some(::std::sync::Arc::new(sink));
+                self
+            }
+
         }
     };
 }
@@ -0,0 +1,292 @@
+//! A mock SPI bus shared by several chips, each selected by its own mock
+//! chip select pin.
+
+use super::{
+    cell::Shared,
+    output::{intercept::Pin, mock::MockPin, mock::PinControl},
+    sink::SharedSink,
+    spi::{
+        intercept::{Spi, SpiOpts},
+        mock::SpiError,
+        model::{BoxedModel, SpiDeviceModel},
+    },
+    Mock,
+};
+use crate::{Polarity, Transfer};
+use std::{borrow::ToOwned, boxed::Box, fmt, string::String, vec::Vec};
+
+/// Describes a transfer on a [`MockBus`] which did not select exactly one
+/// chip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusViolation {
+    /// No chip was selected.
+    NoneSelected { tx: Vec<u8> },
+    /// Several chips were selected at once.
+    Contention { selected: Vec<String>, tx: Vec<u8> },
+}
+
+impl fmt::Display for BusViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusViolation::NoneSelected { tx } => {
+                write!(f, "Transfer with no chip selected: {:02X?}", tx)
+            }
+            BusViolation::Contention { selected, tx } => write!(
+                f,
+                "Transfer with {} chips selected ({}): {:02X?}",
+                selected.len(),
+                selected.join(", "),
+                tx
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BusViolation {}
+
+struct BusDevice {
+    name: String,
+    active: bool,
+    selected: bool,
+    model: BoxedModel,
+}
+
+impl fmt::Debug for BusDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BusDevice")
+            .field("name", &self.name)
+            .field("active", &self.active)
+            .field("selected", &self.selected)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Holds the underlying state shared by a [MockBus], its [BusControl] and
+/// the chip select pins.
+#[derive(Debug, Default)]
+struct MockBusState {
+    devices: Vec<BusDevice>,
+    violations: Vec<BusViolation>,
+}
+
+impl MockBusState {
+    /// Track the chip select pin of a device, notifying its model when it
+    /// is selected or deselected.
+    fn set_cs(&mut self, index: usize, value: bool) -> Result<(), SpiError> {
+        let device = &mut self.devices[index];
+        let selected = value == device.active;
+
+        if selected == device.selected {
+            return Ok(());
+        }
+
+        device.selected = selected;
+        match selected {
+            true => device.model.on_select(),
+            false => device.model.on_deselect(),
+        }
+    }
+}
+
+impl Transfer<u8> for MockBusState {
+    type Error = SpiError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        let mut selected = self.devices.iter_mut().filter(|device| device.selected);
+
+        let device = match (selected.next(), selected.next()) {
+            (Some(device), None) => device,
+            (None, _) => {
+                self.violations
+                    .push(BusViolation::NoneSelected { tx: words.to_vec() });
+                return Err(SpiError::Violation);
+            }
+            (Some(_), Some(_)) => {
+                let selected = self
+                    .devices
+                    .iter()
+                    .filter(|device| device.selected)
+                    .map(|device| device.name.clone())
+                    .collect();
+
+                self.violations.push(BusViolation::Contention {
+                    selected,
+                    tx: words.to_vec(),
+                });
+                return Err(SpiError::Violation);
+            }
+        };
+
+        device.model.exchange(words)?;
+        Ok(words)
+    }
+}
+
+/// Transfer interface for a mock SPI bus. Each transfer reaches only the
+/// chip whose chip select pin is active.
+#[derive(Debug)]
+pub struct MockBus {
+    bus: Shared<MockBusState>,
+}
+
+impl Transfer<u8> for MockBus {
+    type Error = SpiError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.bus.borrow_mut().transfer(words)
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::ErrorType for MockBus {
+    type Error = SpiError;
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::SpiBus<u8> for MockBus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.fill(0x00);
+        Transfer::transfer(self, words).and(Ok(()))
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        Transfer::transfer(self, &mut words.to_vec()).and(Ok(()))
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let mut words = write.to_vec();
+        words.resize(read.len().max(write.len()), 0x00);
+        Transfer::transfer(self, &mut words)?;
+
+        let len = read.len();
+        read.copy_from_slice(&words[..len]);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        Transfer::transfer(self, words).and(Ok(()))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Developer controls for a mock SPI bus.
+#[derive(Debug)]
+pub struct BusControl {
+    bus: Shared<MockBusState>,
+    opts: SpiOpts,
+    sink: Option<SharedSink>,
+}
+
+impl BusControl {
+    /// Attach a chip to the bus, and create its mock chip select pin. The
+    /// pin starts idle for the polarity. A model error when the chip is
+    /// selected or deselected fails the pin change.
+    pub fn device<M: SpiDeviceModel + 'static>(
+        &self,
+        name: &str,
+        polarity: Polarity,
+        model: M,
+    ) -> (Pin<MockPin>, PinControl) {
+        let active = polarity == Polarity::IdleLow;
+        let index = {
+            let mut bus = self.bus.borrow_mut();
+            bus.devices.push(BusDevice {
+                name: name.to_owned(),
+                active,
+                selected: false,
+                model: Box::new(model),
+            });
+            bus.devices.len() - 1
+        };
+
+        let mut builder = Mock::pin(name).with_value(!active);
+        if !self.opts.log {
+            builder = builder.without_log();
+        }
+
+        let (mut pin, control) = builder.init();
+        if let Some(sink) = &self.sink {
+            pin.set_sink(sink.clone());
+        }
+
+        let bus = self.bus.clone();
+        control.set_listener(Box::new(move |value| {
+            bus.borrow_mut().set_cs(index, value).or(Err(()))
+        }));
+
+        (pin, control)
+    }
+
+    /// Tell every model the clock speed has changed, as a transport with
+    /// clock speed control would.
+    pub fn set_clock_speed(&self, speed: u32) -> Result<(), SpiError> {
+        self.bus
+            .borrow_mut()
+            .devices
+            .iter_mut()
+            .try_for_each(|device| device.model.on_clock_speed(speed))
+    }
+
+    /// Get the names of the chips which are currently selected.
+    pub fn get_selected(&self) -> Vec<String> {
+        self.bus
+            .borrow()
+            .devices
+            .iter()
+            .filter(|device| device.selected)
+            .map(|device| device.name.clone())
+            .collect()
+    }
+
+    /// Get the transfers which did not select exactly one chip. These
+    /// transfers fail with [`SpiError::Violation`].
+    pub fn get_violations(&self) -> Vec<BusViolation> {
+        self.bus.borrow().violations.clone()
+    }
+
+    /// Forget the recorded violations.
+    pub fn clear_violations(&self) -> &Self {
+        self.bus.borrow_mut().violations.clear();
+        self
+    }
+
+    /// Check that no violation has been recorded, returning the first.
+    pub fn verify(&self) -> Result<(), BusViolation> {
+        match self.bus.borrow().violations.first() {
+            Some(violation) => Err(violation.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+builder!(MockBusBuilder<SpiOpts> + Debug {});
+
+impl MockBusBuilder {
+    /// Print Tx/Rx bytes to stdout after transfer.
+    pub fn with_byte_log(mut self) -> Self {
+        self.opts.log = true;
+        self.opts.bytes = true;
+        self
+    }
+
+    /// Create the mock bus and controller. Chip select pins created by the
+    /// controller share the logging options and sink of the bus.
+    pub fn init(self) -> (Spi<MockBus>, BusControl) {
+        let bus = Shared::new(MockBusState::default());
+        let control = BusControl {
+            bus: bus.clone(),
+            opts: self.opts,
+            sink: self.sink.clone(),
+        };
+
+        let mut spi = Spi::new(self.name, MockBus { bus }, Shared::new(self.opts));
+        if let Some(sink) = self.sink {
+            spi.set_sink(sink);
+        }
+
+        (spi, control)
+    }
+}
@@ -0,0 +1,306 @@
+use super::{
+    cell::Shared,
+    clock::{Clock, VirtualClock},
+    spi::mock::BoxedGenerator,
+};
+use std::{
+    borrow::ToOwned,
+    boxed::Box,
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    string::{String, ToString},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+    vec,
+    vec::Vec,
+};
+
+/// An event recorded by an intercept. Times are measured from the start of
+/// the capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// A successful transfer.
+    Transfer {
+        time: Duration,
+        tx: Vec<u8>,
+        rx: Vec<u8>,
+    },
+    /// A failed transfer.
+    Error { time: Duration, tx: Vec<u8> },
+    /// A pin was set high or low.
+    Edge {
+        time: Duration,
+        pin: String,
+        high: bool,
+    },
+    /// The clock speed was set, in Hz.
+    ClockSpeed { time: Duration, speed: u32 },
+}
+
+/// An in-memory recording of intercepted traffic, shared by the intercepts
+/// writing to it. A capture can be saved to a file, then replayed by mock
+/// SPI.
+///
+/// ```ignore
+/// let capture = Capture::new();
+/// let spi = Intercept::spi("Sensor").with_capture(&capture).init_bus(real_spi);
+/// let cs = Intercept::pin("SensorCS").with_capture(&capture).init(real_cs_pin);
+///
+/// // ... run the driver against the real sensor, then:
+/// capture.save("sensor.capture")?;
+///
+/// // Later, without hardware:
+/// let (spi, spi_control) = Mock::spi("Sensor").from_capture("sensor.capture")?.init();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Capture {
+    inner: Shared<CaptureInner>,
+}
+
+#[derive(Debug)]
+struct CaptureInner {
+    clock: Clock,
+    start: Instant,
+    events: Vec<CaptureEvent>,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Capture {
+    /// Create an empty capture. Event times are measured from now.
+    pub fn new() -> Self {
+        Self::from_events(Vec::new())
+    }
+
+    /// Create an empty capture. Event times are measured on the virtual
+    /// clock, from its current time.
+    pub fn with_clock(clock: &VirtualClock) -> Self {
+        let capture = Self::new();
+        let mut inner = capture.inner.borrow_mut();
+        inner.clock = Clock::Virtual(clock.clone());
+        inner.start = clock.now();
+        drop(inner);
+
+        capture
+    }
+
+    /// Create a capture holding the events.
+    pub fn from_events(events: Vec<CaptureEvent>) -> Self {
+        Self {
+            inner: Shared::new(CaptureInner {
+                clock: Clock::Real,
+                start: Instant::now(),
+                events,
+            }),
+        }
+    }
+
+    /// Get the recorded events.
+    pub fn events(&self) -> Vec<CaptureEvent> {
+        self.inner.borrow().events.clone()
+    }
+
+    /// Forget the recorded events.
+    pub fn clear(&self) -> &Self {
+        self.inner.borrow_mut().events.clear();
+        self
+    }
+
+    pub(crate) fn record_transfer(&self, tx: &[u8], rx: Option<&[u8]>) {
+        let mut inner = self.inner.borrow_mut();
+        let time = inner.clock.now() - inner.start;
+        let event = match rx {
+            Some(rx) => CaptureEvent::Transfer {
+                time,
+                tx: tx.to_vec(),
+                rx: rx.to_vec(),
+            },
+            None => CaptureEvent::Error {
+                time,
+                tx: tx.to_vec(),
+            },
+        };
+
+        inner.events.push(event);
+    }
+
+    pub(crate) fn record_edge(&self, pin: &str, high: bool) {
+        let mut inner = self.inner.borrow_mut();
+        let time = inner.clock.now() - inner.start;
+
+        inner.events.push(CaptureEvent::Edge {
+            time,
+            pin: pin.to_owned(),
+            high,
+        });
+    }
+
+    pub(crate) fn record_clock_speed(&self, speed: u32) {
+        let mut inner = self.inner.borrow_mut();
+        let time = inner.clock.now() - inner.start;
+
+        inner.events.push(CaptureEvent::ClockSpeed { time, speed });
+    }
+
+    /// Write the events as text, one per line, with times in nanoseconds.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for event in self.inner.borrow().events.iter() {
+            let line = match event {
+                CaptureEvent::Transfer { time, tx, rx } => {
+                    format_line(time, "transfer", &[&hex(tx), &hex(rx)])
+                }
+                CaptureEvent::Error { time, tx } => format_line(time, "error", &[&hex(tx)]),
+                CaptureEvent::Edge { time, pin, high } => {
+                    format_line(time, "edge", &[if *high { "high" } else { "low" }, pin])
+                }
+                CaptureEvent::ClockSpeed { time, speed } => {
+                    format_line(time, "clock", &[&speed.to_string()])
+                }
+            };
+
+            writeln!(writer, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read events written by [`write_to`](Self::write_to).
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut events = Vec::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            events.push(parse_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    std::format!("Invalid capture line: {}", line),
+                )
+            })?);
+        }
+
+        Ok(Self::from_events(events))
+    }
+
+    /// Save the events to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Load events saved with [`save`](Self::save).
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Create a generator for mock SPI which replays the recorded Rx bytes.
+    ///
+    /// Each transfer is answered by the next recorded transfer with the same
+    /// Tx bytes, searching from after the last match and then wrapping
+    /// around. Transfers which were never recorded receive `0x00` bytes.
+    pub fn generator(&self) -> BoxedGenerator {
+        let transfers: Vec<(Vec<u8>, Vec<u8>)> = self
+            .inner
+            .borrow()
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                CaptureEvent::Transfer { tx, rx, .. } => Some((tx.clone(), rx.clone())),
+                _ => None,
+            })
+            .collect();
+        let next = AtomicUsize::new(0);
+
+        Box::new(move |words: &[u8]| {
+            let start = next.load(Ordering::Relaxed);
+            let len = transfers.len();
+
+            (0..len)
+                .map(|offset| (start + offset) % len)
+                .find(|index| transfers[*index].0 == words)
+                .map_or_else(
+                    || vec![0x00; words.len()],
+                    |index| {
+                        next.store(index + 1, Ordering::Relaxed);
+                        transfers[index].1.clone()
+                    },
+                )
+        })
+    }
+}
+
+fn format_line(time: &Duration, kind: &str, fields: &[&str]) -> String {
+    let mut line = std::format!("{} {}", time.as_nanos(), kind);
+    for field in fields {
+        write!(line, " {}", field).ok();
+    }
+
+    line
+}
+
+fn parse_line(line: &str) -> Option<CaptureEvent> {
+    let mut fields = line.splitn(4, ' ');
+    let time = Duration::from_nanos(fields.next()?.parse().ok()?);
+
+    match fields.next()? {
+        "transfer" => Some(CaptureEvent::Transfer {
+            time,
+            tx: parse_hex(fields.next()?)?,
+            rx: parse_hex(fields.next()?)?,
+        }),
+        "error" => Some(CaptureEvent::Error {
+            time,
+            tx: parse_hex(fields.next()?)?,
+        }),
+        "edge" => Some(CaptureEvent::Edge {
+            time,
+            high: match fields.next()? {
+                "high" => true,
+                "low" => false,
+                _ => return None,
+            },
+            pin: fields.next()?.to_owned(),
+        }),
+        "clock" => Some(CaptureEvent::ClockSpeed {
+            time,
+            speed: fields.next()?.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// Bytes as lowercase hex, or `-` when empty.
+fn hex(bytes: &[u8]) -> String {
+    match bytes.is_empty() {
+        true => "-".to_owned(),
+        false => bytes.iter().fold(String::new(), |mut hex, byte| {
+            write!(hex, "{:02x}", byte).ok();
+            hex
+        }),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex == "-" {
+        return Some(Vec::new());
+    }
+
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,30 @@
+//! The shared state behind dev types: an [`Arc`] and a [`Mutex`], so mocks,
+//! intercepts and their controls can be moved to other threads or tasks.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A value shared by a device and its controller.
+#[derive(Debug, Default)]
+pub struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    /// Lock the value. A lock poisoned by a panicking test is recovered.
+    pub fn borrow(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the value. A lock poisoned by a panicking test is recovered.
+    pub fn borrow_mut(&self) -> MutexGuard<'_, T> {
+        self.borrow()
+    }
+}
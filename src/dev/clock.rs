@@ -0,0 +1,75 @@
+use super::cell::Shared;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A clock which only moves when advanced, shared by the mocks and delays
+/// using it. Mocks with a virtual clock advance it instead of sleeping, so
+/// timing-dependent tests run quickly and deterministically.
+///
+/// Times are reported as [`Instant`]s offset from the moment the clock was
+/// created, so they can be compared with other recorded times.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    base: Instant,
+    elapsed: Shared<Duration>,
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            elapsed: Shared::new(Duration::ZERO),
+        }
+    }
+
+    /// The current time.
+    pub fn now(&self) -> Instant {
+        self.base + *self.elapsed.borrow()
+    }
+
+    /// The time elapsed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) -> &Self {
+        *self.elapsed.borrow_mut() += duration;
+        self
+    }
+}
+
+/// The source of time for a mock: real time, or a [`VirtualClock`].
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Clock {
+    #[default]
+    Real,
+    Virtual(VirtualClock),
+}
+
+impl Clock {
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::Real => Instant::now(),
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+
+    /// Sleep the current thread, or advance the virtual clock.
+    pub fn sleep(&self, duration: Duration) {
+        match self {
+            Clock::Real => thread::sleep(duration),
+            Clock::Virtual(clock) => {
+                clock.advance(duration);
+            }
+        }
+    }
+}
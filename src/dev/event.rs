@@ -0,0 +1,216 @@
+//! Typed events for every operation passing through an SPI intercept.
+
+use super::cell::Shared;
+use crate::{BitOrder, ErrorKind, Mode, Polarity};
+use embedded_hal::spi::Phase;
+use std::{fmt, string::String, sync::Arc, time::Instant, vec::Vec};
+
+/// An operation seen by an SPI intercept.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SpiOperation {
+    /// A transfer, which selects and deselects the chip itself when chip
+    /// selection can be controlled.
+    Transfer,
+    Select,
+    Deselect,
+    RawTransfer,
+    RawTransferOrDeselect,
+    SetClockSpeed(u32),
+    SetSpiMode(Mode),
+    SetBitOrder(BitOrder),
+    /// The number of bits per word.
+    SetWordSize(u8),
+    DelayUs(u32),
+    /// A transaction of the given number of operations.
+    Transaction(usize),
+}
+
+impl SpiOperation {
+    /// Whether the operation exchanges bytes.
+    pub fn is_transfer(&self) -> bool {
+        matches!(
+            self,
+            SpiOperation::Transfer
+                | SpiOperation::RawTransfer
+                | SpiOperation::RawTransferOrDeselect
+                | SpiOperation::Transaction(_)
+        )
+    }
+}
+
+impl fmt::Debug for SpiOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpiOperation::Transfer => write!(f, "Transfer"),
+            SpiOperation::Select => write!(f, "Select"),
+            SpiOperation::Deselect => write!(f, "Deselect"),
+            SpiOperation::RawTransfer => write!(f, "RawTransfer"),
+            SpiOperation::RawTransferOrDeselect => write!(f, "RawTransferOrDeselect"),
+            SpiOperation::SetClockSpeed(speed) => write!(f, "SetClockSpeed({})", speed),
+            // `Mode` does not implement `Debug`, so print its name
+            SpiOperation::SetSpiMode(mode) => {
+                let polarity = u8::from(mode.polarity == Polarity::IdleHigh);
+                let phase = u8::from(mode.phase == Phase::CaptureOnSecondTransition);
+                write!(f, "SetSpiMode(MODE_{})", polarity << 1 | phase)
+            }
+            SpiOperation::SetBitOrder(order) => write!(f, "SetBitOrder({:?})", order),
+            SpiOperation::SetWordSize(bits) => write!(f, "SetWordSize({})", bits),
+            SpiOperation::DelayUs(us) => write!(f, "DelayUs({})", us),
+            SpiOperation::Transaction(len) => write!(f, "Transaction({})", len),
+        }
+    }
+}
+
+/// An operation seen by the named SPI intercept, and its outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpiEvent {
+    /// When the operation completed.
+    pub time: Instant,
+    /// The name of the intercept.
+    pub name: String,
+    /// The position of the event among those of the intercept, from 0.
+    pub sequence: u64,
+    pub operation: SpiOperation,
+    /// The number of bytes the operation exchanged, or would have.
+    pub bytes: usize,
+    /// Whether the chip was selected through the intercept when the
+    /// operation completed.
+    pub selected: bool,
+    /// The kind of error the operation failed with, if it failed. Errors of
+    /// an intercepted bus, rather than a transport, are recorded as
+    /// [`ErrorKind::Transfer`].
+    pub error: Option<ErrorKind>,
+}
+
+impl SpiEvent {
+    /// Whether the operation succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Receives the events of SPI intercepts.
+///
+/// Implemented for closures taking a [`SpiEvent`], and by [`EventRecorder`].
+pub trait EventSubscriber: Send + Sync {
+    fn event(&self, event: &SpiEvent);
+}
+
+impl<F: Fn(&SpiEvent) + Send + Sync> EventSubscriber for F {
+    fn event(&self, event: &SpiEvent) {
+        self(event)
+    }
+}
+
+impl fmt::Debug for dyn EventSubscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "EventSubscriber")
+    }
+}
+
+/// A cloneable handle to an [`EventSubscriber`], used by intercepts.
+pub(crate) type SharedSubscriber = Arc<dyn EventSubscriber>;
+
+/// Records the events of the intercepts it subscribes to, in the order they
+/// happened, for queries in tests.
+///
+/// ```
+/// use embedded_hal::spi::MODE_3;
+/// use rpio_utils::{*, dev::*};
+///
+/// let (spi, _) = Mock::spi("MockSPI").without_log().init();
+/// let (cs, cs_control) = Mock::pin("MockCS").without_log().init();
+/// let recorder = EventRecorder::new();
+/// let mut spi = Intercept::spi("Sensor")
+///     .without_log()
+///     .with_subscriber(recorder.clone())
+///     .init(Transport::hal(spi).with_cs(cs).init());
+///
+/// // A driver sets the mode while the chip is selected, before exchanging
+/// spi.select().unwrap();
+/// spi.set_spi_mode(MODE_3).unwrap_err();
+/// spi.raw_transfer(&mut [0x01, 0x02]).unwrap();
+/// spi.deselect().unwrap();
+///
+/// assert!(recorder.happened_before(
+///     |event| event.operation == SpiOperation::SetSpiMode(MODE_3) && event.selected,
+///     |event| event.operation.is_transfer(),
+/// ));
+///
+/// // The hal transport cannot change the mode, so the event records why
+/// let failed = recorder.first(|event| !event.is_ok()).unwrap();
+/// assert_eq!(failed.operation, SpiOperation::SetSpiMode(MODE_3));
+/// assert_eq!(failed.error, Some(ErrorKind::NotImplemented));
+///
+/// // A transfer failing to select the chip records the kind of its error
+/// cs_control.set_error(PinError::SetLow);
+/// spi.transfer(&mut [0x03]).unwrap_err();
+/// let failed = recorder.first(|event| event.operation == SpiOperation::Transfer).unwrap();
+/// assert_eq!(failed.error, Some(ErrorKind::ChipSelect));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventRecorder {
+    events: Shared<Vec<SpiEvent>>,
+}
+
+impl EventRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the recorded events.
+    pub fn events(&self) -> Vec<SpiEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// Forget the recorded events.
+    pub fn clear(&self) -> &Self {
+        self.events.borrow_mut().clear();
+        self
+    }
+
+    /// Get the recorded events matching the predicate.
+    pub fn filter<P: Fn(&SpiEvent) -> bool>(&self, predicate: P) -> Vec<SpiEvent> {
+        self.events
+            .borrow()
+            .iter()
+            .filter(|event| predicate(event))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the first recorded event matching the predicate.
+    pub fn first<P: Fn(&SpiEvent) -> bool>(&self, predicate: P) -> Option<SpiEvent> {
+        self.events
+            .borrow()
+            .iter()
+            .find(|event| predicate(event))
+            .cloned()
+    }
+
+    /// Get the number of recorded events of the operation.
+    pub fn count(&self, operation: SpiOperation) -> usize {
+        self.filter(|event| event.operation == operation).len()
+    }
+
+    /// Whether an event matching `first` was recorded before any event
+    /// matching `then`. True if `first` matched and `then` never did.
+    pub fn happened_before<A, B>(&self, first: A, then: B) -> bool
+    where
+        A: Fn(&SpiEvent) -> bool,
+        B: Fn(&SpiEvent) -> bool,
+    {
+        let events = self.events.borrow();
+        let Some(first) = events.iter().position(first) else {
+            return false;
+        };
+
+        events.iter().position(then).is_none_or(|then| first < then)
+    }
+}
+
+impl EventSubscriber for EventRecorder {
+    fn event(&self, event: &SpiEvent) {
+        self.events.borrow_mut().push(event.clone());
+    }
+}
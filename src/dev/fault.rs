@@ -0,0 +1,249 @@
+//! Random faults for mock SPI and mock output pins, driven by a seeded
+//! generator so that a failing run can be reproduced.
+//!
+//! Faults are rolled in the order the mock is used, so the same seed and
+//! the same sequence of calls inject the same faults. Probabilities of zero
+//! draw nothing from the generator, so enabling one fault does not change
+//! when the others occur.
+
+use std::vec::Vec;
+
+/// Which faults to inject, and how often.
+///
+/// Probabilities range from 0 (never) to 1 (always). For mock SPI, a failed
+/// transfer returns [`SpiError::Transfer`](super::spi::mock::SpiError), or
+/// [`SpiError::Partial`](super::spi::mock::SpiError) after some bytes, and
+/// Rx faults apply to the bytes exchanged before the failure. For mock
+/// output pins, a failed change returns the [`PinError`](super::PinError)
+/// of the change, and the value is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FaultPolicy {
+    seed: u64,
+    transfer_error: f64,
+    byte_error: f64,
+    bit_flip: f64,
+    stuck_low: u8,
+    stuck_high: u8,
+    drop_byte: f64,
+    duplicate_byte: f64,
+    pin_error: f64,
+    pin_error_every: usize,
+}
+
+impl FaultPolicy {
+    /// Create a policy without faults.
+    pub const fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            transfer_error: 0.0,
+            byte_error: 0.0,
+            bit_flip: 0.0,
+            stuck_low: 0x00,
+            stuck_high: 0x00,
+            drop_byte: 0.0,
+            duplicate_byte: 0.0,
+            pin_error: 0.0,
+            pin_error_every: 0,
+        }
+    }
+
+    /// The seed of the generator.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Fail whole transfers before any byte is exchanged.
+    pub const fn with_transfer_error(mut self, probability: f64) -> Self {
+        self.transfer_error = probability;
+        self
+    }
+
+    /// Fail transfers at each byte.
+    pub const fn with_byte_error(mut self, probability: f64) -> Self {
+        self.byte_error = probability;
+        self
+    }
+
+    /// Flip one random bit of each Rx byte.
+    pub const fn with_bit_flip(mut self, probability: f64) -> Self {
+        self.bit_flip = probability;
+        self
+    }
+
+    /// Hold the bits of the mask low in every Rx byte, as a MISO line stuck
+    /// at 0 for those bits.
+    pub const fn with_stuck_low(mut self, mask: u8) -> Self {
+        self.stuck_low = mask;
+        self
+    }
+
+    /// Hold the bits of the mask high in every Rx byte, as a MISO line stuck
+    /// at 1 for those bits.
+    pub const fn with_stuck_high(mut self, mask: u8) -> Self {
+        self.stuck_high = mask;
+        self
+    }
+
+    /// Drop each Rx byte, shifting the following bytes earlier and padding
+    /// the end with zeros.
+    pub const fn with_drop_byte(mut self, probability: f64) -> Self {
+        self.drop_byte = probability;
+        self
+    }
+
+    /// Duplicate each Rx byte, shifting the following bytes later.
+    pub const fn with_duplicate_byte(mut self, probability: f64) -> Self {
+        self.duplicate_byte = probability;
+        self
+    }
+
+    /// Fail each change of a pin's value.
+    pub const fn with_pin_error(mut self, probability: f64) -> Self {
+        self.pin_error = probability;
+        self
+    }
+
+    /// Fail every nth change of a pin's value. 0 disables this fault.
+    pub const fn with_pin_error_every(mut self, n: usize) -> Self {
+        self.pin_error_every = n;
+        self
+    }
+}
+
+/// The number of faults injected by a mock, by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FaultCounts {
+    pub transfer_errors: usize,
+    pub byte_errors: usize,
+    pub bit_flips: usize,
+    /// Rx bytes changed by stuck bits.
+    pub stuck_bytes: usize,
+    pub dropped_bytes: usize,
+    pub duplicated_bytes: usize,
+    pub pin_errors: usize,
+}
+
+impl FaultCounts {
+    /// The total number of faults injected.
+    pub fn total(&self) -> usize {
+        self.transfer_errors
+            + self.byte_errors
+            + self.bit_flips
+            + self.stuck_bytes
+            + self.dropped_bytes
+            + self.duplicated_bytes
+            + self.pin_errors
+    }
+}
+
+/// A SplitMix64 generator. Small and fast, not cryptographic.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Roll for an event of the given probability.
+    fn chance(&mut self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < probability
+    }
+}
+
+/// Applies a [`FaultPolicy`] and counts the injected faults.
+#[derive(Debug, Clone)]
+pub(crate) struct FaultInjector {
+    policy: FaultPolicy,
+    rng: Rng,
+    changes: usize,
+    counts: FaultCounts,
+}
+
+impl FaultInjector {
+    pub fn new(policy: FaultPolicy) -> Self {
+        Self {
+            policy,
+            rng: Rng(policy.seed),
+            changes: 0,
+            counts: FaultCounts::default(),
+        }
+    }
+
+    pub fn counts(&self) -> FaultCounts {
+        self.counts
+    }
+
+    /// Roll for a failed transfer of `len` bytes, returning the index of the
+    /// byte it fails at.
+    pub fn fail_at(&mut self, len: usize) -> Option<usize> {
+        if self.rng.chance(self.policy.transfer_error) {
+            self.counts.transfer_errors += 1;
+            return Some(0);
+        }
+
+        let index = (0..len).find(|_| self.rng.chance(self.policy.byte_error))?;
+        self.counts.byte_errors += 1;
+        Some(index)
+    }
+
+    /// Drop, duplicate, flip and stick the bits of Rx bytes, in that order.
+    pub fn corrupt(&mut self, words: &mut [u8]) {
+        let policy = self.policy;
+
+        if policy.drop_byte > 0.0 || policy.duplicate_byte > 0.0 {
+            let mut rx = Vec::with_capacity(words.len());
+            for word in words.iter() {
+                if self.rng.chance(policy.drop_byte) {
+                    self.counts.dropped_bytes += 1;
+                    continue;
+                }
+
+                rx.push(*word);
+                if self.rng.chance(policy.duplicate_byte) {
+                    self.counts.duplicated_bytes += 1;
+                    rx.push(*word);
+                }
+            }
+
+            rx.resize(words.len(), 0x00);
+            words.copy_from_slice(&rx[..words.len()]);
+        }
+
+        for word in words.iter_mut() {
+            if self.rng.chance(policy.bit_flip) {
+                self.counts.bit_flips += 1;
+                *word ^= 1 << (self.rng.next_u64() % 8);
+            }
+
+            let stuck = (*word & !policy.stuck_low) | policy.stuck_high;
+            if stuck != *word {
+                self.counts.stuck_bytes += 1;
+                *word = stuck;
+            }
+        }
+    }
+
+    /// Roll for a failed change of a pin's value.
+    pub fn fail_pin(&mut self) -> bool {
+        self.changes += 1;
+
+        let every = self.policy.pin_error_every;
+        let fail = (every > 0 && self.changes.is_multiple_of(every))
+            || self.rng.chance(self.policy.pin_error);
+        if fail {
+            self.counts.pin_errors += 1;
+        }
+
+        fail
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    dev::{
+        cell::Shared,
+        output::intercept::PinOpts,
+        sink::{default_sink, LogSink, SharedSink},
+    },
+    InputPin,
+};
+use std::{borrow::ToOwned, string::String, sync::Arc};
+
+/// Intercepts [`InputPin`](InputPin), providing logging of reads.
+pub struct Pin<P: InputPin> {
+    name: String,
+    pin: P,
+    opts: Shared<PinOpts>,
+    sink: SharedSink,
+}
+
+impl<P: InputPin> Pin<P> {
+    pub fn new(name: String, pin: P, opts: Shared<PinOpts>) -> Self {
+        Self {
+            name,
+            pin,
+            opts,
+            sink: default_sink(),
+        }
+    }
+
+    /// Set whether events are printed to stdout.
+    pub fn set_log(&mut self, log: bool) {
+        self.opts.borrow_mut().log = log;
+    }
+
+    /// Send events to the sink instead of stdout.
+    pub fn set_sink<L: LogSink + 'static>(&mut self, sink: L) {
+        self.sink = Arc::new(sink);
+    }
+
+    fn log_read<E>(&self, result: &Result<bool, E>) {
+        if self.opts.borrow().log {
+            match result {
+                Ok(true) => self.sink.log(&self.name, "read high"),
+                Ok(false) => self.sink.log(&self.name, "read low"),
+                Err(_) => self.sink.log(&self.name, "Error (read failed)"),
+            };
+        }
+    }
+}
+
+impl<P: InputPin> InputPin for Pin<P> {
+    type Error = P::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let result = self.pin.is_high();
+        self.log_read(&result);
+        result
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<P: InputPin + _eh1::digital::ErrorType> _eh1::digital::ErrorType for Pin<P> {
+    type Error = <P as _eh1::digital::ErrorType>::Error;
+}
+
+#[cfg(feature = "eh1")]
+impl<P: InputPin + _eh1::digital::InputPin> _eh1::digital::InputPin for Pin<P> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        let result = _eh1::digital::InputPin::is_high(&mut self.pin);
+        self.log_read(&result);
+        result
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        _eh1::digital::InputPin::is_high(self).map(|high| !high)
+    }
+}
+
+builder!(InterceptBuilder<PinOpts> + Clone, Debug {});
+
+impl InterceptBuilder {
+    pub fn init<P: InputPin>(self, pin: P) -> Pin<P> {
+        let mut pin = Pin::new(self.name, pin, Shared::new(self.opts));
+
+        if let Some(sink) = self.sink {
+            pin.set_sink(sink);
+        }
+
+        pin
+    }
+}
@@ -0,0 +1,245 @@
+use super::{
+    super::{
+        cell::Shared,
+        clock::{Clock, VirtualClock},
+    },
+    intercept::Pin,
+};
+use crate::{dev::output::intercept::PinOpts, InputPin};
+use std::{
+    borrow::ToOwned, boxed::Box, collections::VecDeque, fmt, string::String, time::Instant,
+    vec::Vec,
+};
+
+/// Read interface for mock input pin.
+#[derive(Debug)]
+pub struct MockInputPin {
+    dev: Shared<MockInputPinDevice>,
+}
+
+impl MockInputPin {
+    fn new(dev: Shared<MockInputPinDevice>) -> Self {
+        Self { dev }
+    }
+}
+
+impl InputPin for MockInputPin {
+    type Error = InputError;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.dev.borrow_mut().read()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::ErrorType for MockInputPin {
+    type Error = InputError;
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::InputPin for MockInputPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        InputPin::is_high(self)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        InputPin::is_low(self)
+    }
+}
+
+/// An enum of mock input pin errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputError {
+    Read,
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::Error for InputError {
+    fn kind(&self) -> _eh1::digital::ErrorKind {
+        _eh1::digital::ErrorKind::Other
+    }
+}
+
+/// Indicates that the function can be used to generate levels for a mock
+/// input pin.
+pub trait LevelGenerator: Fn() -> bool + Send + Sync {}
+
+impl<F: Fn() -> bool + Send + Sync> LevelGenerator for F {}
+
+/// A boxed level generator for a mock input pin.
+pub type BoxedLevelGenerator = Box<dyn LevelGenerator>;
+
+impl fmt::Debug for dyn LevelGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Function (Level Generator)")
+    }
+}
+
+/// Holds the underlying state shared by [MockInputPin] and [InputControl].
+#[derive(Debug)]
+pub struct MockInputPinDevice {
+    level: bool,
+    sequence: VecDeque<bool>,
+    generator: Option<BoxedLevelGenerator>,
+    error: Option<InputError>,
+    reads: Vec<(Instant, bool)>,
+    clock: Clock,
+}
+
+impl MockInputPinDevice {
+    fn new() -> Self {
+        Self {
+            level: false,
+            sequence: VecDeque::new(),
+            generator: None,
+            error: None,
+            reads: Vec::new(),
+            clock: Clock::Real,
+        }
+    }
+
+    fn read(&mut self) -> Result<bool, InputError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        let level = match (&self.generator, self.sequence.pop_front()) {
+            (Some(generator), _) => generator(),
+            (None, Some(level)) => {
+                self.level = level;
+                level
+            }
+            (None, None) => self.level,
+        };
+
+        self.reads.push((self.clock.now(), level));
+        Ok(level)
+    }
+}
+
+/// Developer controls for mock input pin.
+#[derive(Debug)]
+pub struct InputControl {
+    pin: Shared<MockInputPinDevice>,
+}
+
+impl InputControl {
+    fn new(pin: Shared<MockInputPinDevice>) -> Self {
+        Self { pin }
+    }
+
+    /// Set the level returned by reads, replacing any sequence or generator.
+    pub fn set_level(&self, high: bool) -> &Self {
+        let mut pin = self.pin.borrow_mut();
+        pin.level = high;
+        pin.sequence.clear();
+        pin.generator = None;
+        self
+    }
+
+    /// Return these levels from successive reads, replacing any generator.
+    /// Once the sequence is exhausted, the last level is kept.
+    pub fn set_sequence<I: IntoIterator<Item = bool>>(&self, levels: I) -> &Self {
+        let mut pin = self.pin.borrow_mut();
+        pin.sequence = levels.into_iter().collect();
+        pin.generator = None;
+        self
+    }
+
+    /// Use this function to provide the level of each read.
+    pub fn set_generator<F: LevelGenerator + 'static>(&self, generator: F) -> &Self {
+        self.pin.borrow_mut().generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Clear the level generator function (if set).
+    pub fn clear_generator(&self) -> &Self {
+        self.pin.borrow_mut().generator = None;
+        self
+    }
+
+    /// Set a mock error, which occurs on the next read.
+    pub fn set_error(&self, error: InputError) -> &Self {
+        self.pin.borrow_mut().error = Some(error);
+        self
+    }
+
+    /// Clear the mock error (if set).
+    pub fn clear_error(&self) -> &Self {
+        self.pin.borrow_mut().error = None;
+        self
+    }
+
+    /// Get the time and level of each successful read.
+    pub fn get_reads(&self) -> Vec<(Instant, bool)> {
+        self.pin.borrow().reads.clone()
+    }
+
+    /// Forget the recorded reads.
+    pub fn clear_reads(&self) -> &Self {
+        self.pin.borrow_mut().reads.clear();
+        self
+    }
+}
+
+builder!(MockBuilder<PinOpts> + Debug {
+    level: bool = false,
+    sequence: Option<VecDeque<bool>> = None,
+    generator: Option<BoxedLevelGenerator> = None,
+    clock: Clock = Clock::Real,
+});
+
+impl MockBuilder {
+    /// Start at this level. Defaults to low.
+    pub fn with_level(mut self, high: bool) -> Self {
+        self.level = high;
+        self
+    }
+
+    /// Return these levels from successive reads. See
+    /// [`InputControl::set_sequence`].
+    pub fn with_sequence<I: IntoIterator<Item = bool>>(mut self, levels: I) -> Self {
+        self.sequence = Some(levels.into_iter().collect());
+        self
+    }
+
+    /// Use a function to provide the level of each read.
+    pub fn with_generator<F: LevelGenerator + 'static>(mut self, generator: F) -> Self {
+        self.generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Record read times from the virtual clock.
+    pub fn with_clock(mut self, clock: &VirtualClock) -> Self {
+        self.clock = Clock::Virtual(clock.clone());
+        self
+    }
+
+    /// Create the mock input pin and controller.
+    pub fn init(self) -> (Pin<MockInputPin>, InputControl) {
+        let dev = Shared::new(MockInputPinDevice::new());
+        dev.borrow_mut().clock = self.clock;
+        let control = InputControl::new(dev.clone());
+        let mut pin = Pin::new(self.name, MockInputPin::new(dev), Shared::new(self.opts));
+
+        control.set_level(self.level);
+
+        if let Some(sequence) = self.sequence {
+            control.set_sequence(sequence);
+        }
+
+        if let Some(generator) = self.generator {
+            control.pin.borrow_mut().generator = Some(generator);
+        }
+
+        if let Some(sink) = self.sink {
+            pin.set_sink(sink);
+        }
+
+        (pin, control)
+    }
+}
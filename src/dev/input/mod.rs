@@ -0,0 +1,2 @@
+pub mod intercept;
+pub mod mock;
@@ -0,0 +1,422 @@
+//! Byte patterns and assertions for recorded SPI traffic.
+//!
+//! A [`Pattern`] is a list of [`ByteMatcher`]s, each matching one byte, or
+//! a repeated run of bytes like a regular expression. Bytes convert to exact
+//! matchers, so patterns are written as lists:
+//!
+//! ```
+//! use rpio_utils::dev::matcher::*;
+//!
+//! let pattern = Pattern::new([0x03.into(), any_n(3), mask(0x80, 0x00).repeat(1..), rest()]);
+//! assert!(pattern.matches(&[0x03, 0x00, 0x10, 0x00, 0x7f, 0x01]));
+//! assert!(!pattern.matches(&[0x03, 0x00, 0x10, 0x00, 0xff]));
+//! ```
+//!
+//! [`assert_sent!`](crate::assert_sent) and
+//! [`assert_transfer_sequence!`](crate::assert_transfer_sequence) check the
+//! Tx bytes of any [`Traffic`], and print annotated hexdumps on failure. The
+//! matcher functions are in scope inside them.
+
+use super::{capture::Capture, spi::mock::SpiControl, CaptureEvent};
+use core::ops::{Bound, RangeBounds};
+use std::{fmt, format, string::String, vec, vec::Vec};
+
+/// Matches bytes whose masked bits equal a value, repeated between a
+/// minimum and maximum number of times (once by default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteMatcher {
+    mask: u8,
+    value: u8,
+    min: usize,
+    max: usize,
+}
+
+impl ByteMatcher {
+    /// Match bytes whose masked bits equal those of the value.
+    pub const fn new(mask: u8, value: u8) -> Self {
+        Self {
+            mask,
+            value: value & mask,
+            min: 1,
+            max: 1,
+        }
+    }
+
+    /// Whether the byte matches, regardless of repetition.
+    pub const fn accepts(&self, byte: u8) -> bool {
+        byte & self.mask == self.value
+    }
+
+    /// Match exactly `count` bytes.
+    pub const fn times(mut self, count: usize) -> Self {
+        self.min = count;
+        self.max = count;
+        self
+    }
+
+    /// Match a number of bytes in the range, such as `2..=4` or `1..`.
+    pub fn repeat<R: RangeBounds<usize>>(mut self, range: R) -> Self {
+        self.min = match range.start_bound() {
+            Bound::Included(min) => *min,
+            Bound::Excluded(min) => min + 1,
+            Bound::Unbounded => 0,
+        };
+        self.max = match range.end_bound() {
+            Bound::Included(max) => *max,
+            Bound::Excluded(max) => max.saturating_sub(1),
+            Bound::Unbounded => usize::MAX,
+        };
+        self
+    }
+}
+
+impl From<u8> for ByteMatcher {
+    fn from(byte: u8) -> Self {
+        exact(byte)
+    }
+}
+
+impl fmt::Display for ByteMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mask {
+            0xff => write!(f, "{:02x}", self.value)?,
+            0x00 => write!(f, "??")?,
+            mask => write!(f, "{:02x}/{:02x}", self.value, mask)?,
+        }
+
+        match (self.min, self.max) {
+            (1, 1) => Ok(()),
+            (0, usize::MAX) => write!(f, "*"),
+            (min, usize::MAX) => write!(f, "{{{},}}", min),
+            (min, max) if min == max => write!(f, "{{{}}}", min),
+            (min, max) => write!(f, "{{{},{}}}", min, max),
+        }
+    }
+}
+
+/// Match the byte.
+pub const fn exact(byte: u8) -> ByteMatcher {
+    ByteMatcher::new(0xff, byte)
+}
+
+/// Match any byte.
+pub const fn any() -> ByteMatcher {
+    ByteMatcher::new(0x00, 0x00)
+}
+
+/// Match any `count` bytes.
+pub const fn any_n(count: usize) -> ByteMatcher {
+    any().times(count)
+}
+
+/// Match any number of bytes, including none.
+pub const fn rest() -> ByteMatcher {
+    let mut rest = any();
+    rest.min = 0;
+    rest.max = usize::MAX;
+    rest
+}
+
+/// Match bytes whose masked bits equal those of the value.
+pub const fn mask(mask: u8, value: u8) -> ByteMatcher {
+    ByteMatcher::new(mask, value)
+}
+
+/// A sequence of [`ByteMatcher`]s, matching whole transfers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pattern {
+    matchers: Vec<ByteMatcher>,
+}
+
+impl Pattern {
+    pub fn new<I: IntoIterator<Item = ByteMatcher>>(matchers: I) -> Self {
+        Self {
+            matchers: matchers.into_iter().collect(),
+        }
+    }
+
+    /// Whether the pattern matches all of the bytes.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        self.reachable(bytes)[self.matchers.len()][bytes.len()]
+    }
+
+    /// The index of the first byte which cannot match, or the length of the
+    /// bytes if they end too soon. `None` if the pattern matches.
+    pub fn mismatch(&self, bytes: &[u8]) -> Option<usize> {
+        let reachable = self.reachable(bytes);
+        if reachable[self.matchers.len()][bytes.len()] {
+            return None;
+        }
+
+        let furthest = (0..self.matchers.len())
+            .flat_map(|index| (0..=bytes.len()).map(move |start| (index, start)))
+            .filter(|(index, start)| reachable[*index][*start])
+            .map(|(index, start)| start + self.run(index, &bytes[start..]))
+            .max()
+            .unwrap_or(0);
+
+        Some(furthest)
+    }
+
+    /// The number of bytes the matcher accepts in a row, up to its maximum.
+    fn run(&self, index: usize, bytes: &[u8]) -> usize {
+        let matcher = &self.matchers[index];
+        bytes
+            .iter()
+            .take(matcher.max)
+            .take_while(|byte| matcher.accepts(**byte))
+            .count()
+    }
+
+    /// Whether the first `i` matchers can match exactly the first `j`
+    /// bytes, for every `i` and `j`.
+    fn reachable(&self, bytes: &[u8]) -> Vec<Vec<bool>> {
+        let mut reachable = vec![vec![false; bytes.len() + 1]; self.matchers.len() + 1];
+        reachable[0][0] = true;
+
+        for index in 0..self.matchers.len() {
+            let min = self.matchers[index].min;
+
+            for start in 0..=bytes.len() {
+                if !reachable[index][start] {
+                    continue;
+                }
+
+                let run = self.run(index, &bytes[start..]);
+                for count in min..=run {
+                    reachable[index + 1][start + count] = true;
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+impl From<Vec<ByteMatcher>> for Pattern {
+    fn from(matchers: Vec<ByteMatcher>) -> Self {
+        Self::new(matchers)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (index, matcher) in self.matchers.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", matcher)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// A recording of transfers, as their Tx and Rx bytes.
+pub trait Traffic {
+    fn traffic(&self) -> Vec<(Vec<u8>, Vec<u8>)>;
+}
+
+impl Traffic for SpiControl {
+    fn traffic(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.get_traffic()
+    }
+}
+
+/// Only successful transfers are included.
+impl Traffic for Capture {
+    fn traffic(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.events()
+            .into_iter()
+            .filter_map(|event| match event {
+                CaptureEvent::Transfer { tx, rx, .. } => Some((tx, rx)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl<T: Traffic + ?Sized> Traffic for &T {
+    fn traffic(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (**self).traffic()
+    }
+}
+
+/// Describes how recorded traffic differs from the patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrafficMismatch {
+    /// No transfer sent bytes matching the pattern.
+    NotSent {
+        pattern: Pattern,
+        sent: Vec<Vec<u8>>,
+    },
+    /// A transfer of the sequence did not match its pattern.
+    Mismatch {
+        index: usize,
+        pattern: Pattern,
+        actual: Vec<u8>,
+    },
+    /// The number of transfers differs from the number of patterns.
+    Count { expected: usize, actual: usize },
+}
+
+impl fmt::Display for TrafficMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrafficMismatch::NotSent { pattern, sent } => {
+                write!(f, "No transfer matched {}", pattern)?;
+                for (index, tx) in sent.iter().enumerate() {
+                    write!(
+                        f,
+                        "\nTransfer {}:\n{}",
+                        index,
+                        hexdump(tx, pattern.mismatch(tx))
+                    )?;
+                }
+                Ok(())
+            }
+            TrafficMismatch::Mismatch {
+                index,
+                pattern,
+                actual,
+            } => {
+                let at = pattern.mismatch(actual);
+                writeln!(f, "Transfer {} did not match {}", index, pattern)?;
+                match at {
+                    Some(at) if at < actual.len() => writeln!(f, "  differs at byte {}", at)?,
+                    _ => writeln!(f, "  ends too soon ({} bytes)", actual.len())?,
+                }
+                write!(f, "{}", hexdump(actual, at))
+            }
+            TrafficMismatch::Count { expected, actual } => {
+                write!(f, "Expected {} transfers, recorded {}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TrafficMismatch {}
+
+/// Check that some recorded transfer sent bytes matching the pattern.
+pub fn check_sent<T: Traffic + ?Sized>(
+    traffic: &T,
+    pattern: &Pattern,
+) -> Result<(), TrafficMismatch> {
+    let sent: Vec<Vec<u8>> = traffic.traffic().into_iter().map(|(tx, _)| tx).collect();
+
+    match sent.iter().any(|tx| pattern.matches(tx)) {
+        true => Ok(()),
+        false => Err(TrafficMismatch::NotSent {
+            pattern: pattern.clone(),
+            sent,
+        }),
+    }
+}
+
+/// Check that the recorded transfers sent bytes matching the patterns, in
+/// order, and no others.
+pub fn check_transfer_sequence<T: Traffic + ?Sized>(
+    traffic: &T,
+    patterns: &[Pattern],
+) -> Result<(), TrafficMismatch> {
+    let traffic = traffic.traffic();
+
+    for (index, ((tx, _), pattern)) in traffic.iter().zip(patterns).enumerate() {
+        if !pattern.matches(tx) {
+            return Err(TrafficMismatch::Mismatch {
+                index,
+                pattern: pattern.clone(),
+                actual: tx.clone(),
+            });
+        }
+    }
+
+    match traffic.len() == patterns.len() {
+        true => Ok(()),
+        false => Err(TrafficMismatch::Count {
+            expected: patterns.len(),
+            actual: traffic.len(),
+        }),
+    }
+}
+
+/// Bytes in rows of 16, like the byte log of an intercept, with `^^` under
+/// the marked byte.
+fn hexdump(bytes: &[u8], mark: Option<usize>) -> String {
+    let mut lines = Vec::new();
+
+    for (chunk, row) in bytes.chunks(16).enumerate() {
+        let range = format!("{}-{}", chunk * 16, chunk * 16 + row.len());
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        lines.push(format!("{: >12} --> {}", range, hex.join(" ")));
+
+        if let Some(offset) = mark.and_then(|mark| mark.checked_sub(chunk * 16)) {
+            if offset < 16 && offset < row.len() {
+                lines.push(format!("{: >12}     {}^^", "", "   ".repeat(offset)));
+            }
+        }
+    }
+
+    if bytes.is_empty() {
+        lines.push(format!("{: >12} --> (no bytes)", "0-0"));
+    }
+
+    lines.join("\n")
+}
+
+/// Assert that some transfer recorded by a [`Traffic`] sent bytes matching
+/// the pattern. The matcher functions of [`dev::matcher`](crate::dev::matcher)
+/// are in scope.
+///
+/// ```
+/// use rpio_utils::{*, dev::*};
+///
+/// let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+/// let mut spi = Transport::hal(spi).init();
+/// spi.transfer(&mut [0x9f, 0x00, 0x00]).unwrap();
+///
+/// assert_sent!(spi_control, [0x9f, any_n(2)]);
+/// assert_sent!(spi_control, [mask(0xf0, 0x90), rest()]);
+/// ```
+#[macro_export]
+macro_rules! assert_sent {
+    ($traffic:expr, [$($matcher:expr),* $(,)?] $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::dev::matcher::*;
+
+        let pattern = Pattern::new([$(ByteMatcher::from($matcher)),*]);
+        if let Err(err) = check_sent(&$traffic, &pattern) {
+            panic!("{}", err);
+        }
+    }};
+}
+
+/// Assert that the transfers recorded by a [`Traffic`] sent bytes matching
+/// the patterns, in order, and no others. The matcher functions of
+/// [`dev::matcher`](crate::dev::matcher) are in scope.
+///
+/// ```
+/// use rpio_utils::{*, dev::*};
+///
+/// let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+/// let mut spi = Transport::hal(spi).init();
+/// spi.transfer(&mut [0x06]).unwrap();
+/// spi.transfer(&mut [0x02, 0x00, 0x10, 0x00, 0xaa, 0xbb]).unwrap();
+///
+/// assert_transfer_sequence!(spi_control, [
+///     [0x06],
+///     [0x02, any_n(3), rest()],
+/// ]);
+/// ```
+#[macro_export]
+macro_rules! assert_transfer_sequence {
+    ($traffic:expr, [$([$($matcher:expr),* $(,)?]),* $(,)?] $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::dev::matcher::*;
+
+        let patterns = [$(Pattern::new([$(ByteMatcher::from($matcher)),*])),*];
+        if let Err(err) = check_transfer_sequence(&$traffic, &patterns) {
+            panic!("{}", err);
+        }
+    }};
+}
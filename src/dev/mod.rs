@@ -3,13 +3,13 @@
 //!
 //! # Examples
 //!
-//! ```
+//! ```ignore
 //! use rpio_utils::{*, dev::*};
 //!
 //! let real_spi = ...;
 //! let real_cs_pin = ...;
 //!
-//! let spi_int = Intercept::spi("MySPI").init(real_spi);
+//! let spi_int = Intercept::spi("MySPI").init_bus(real_spi);
 //! let pin_int = Intercept::pin("MyCS").init(real_cs_pin);
 //!
 //! // Now logs SPI traffic
@@ -22,37 +22,478 @@
 //! ## Mocks
 //!
 //! ```
-//! use rpio_utils::{*, dev::*};
+//! use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
 //!
 //! let (spi, spi_control) = Mock::spi("MockSPI")
-//!     .with_byte_log()
+//!     .without_log()
 //!     .with_generator(|tx: &[u8]| tx.to_vec())
 //!     .init();
 //!
-//! let (cs, cs_control) = Mock::pin("MockCS").init();
+//! let (cs, cs_control) = Mock::pin("MockCS").without_log().init();
 //!
 //! // Emulated device implementing Transfer<u8>
-//! let spi = Transport::new(spi)
-//!     .with_chip_select(cs)
-//!     .init();
+//! let mut spi = Transport::hal(spi).with_cs(cs).init();
+//! assert_eq!(spi.transfer(&mut [0x01, 0x02]).unwrap(), &[0x01, 0x02]);
 //!
-//! // Introduce a transfer error after 33 bytes:
+//! // Introduce a transfer error after 33 bytes, kept as the source:
 //! spi_control
-//!     .set_error(Error::Transfer)
+//!     .set_error(SpiError::Transfer)
 //!     .set_error_defer_bytes(33);
 //!
+//! let err = spi.transfer(&mut [0x00; 64]).unwrap_err();
+//! assert_eq!(err, ErrorKind::Transfer);
+//! assert_eq!(
+//!     err.device_error(),
+//!     Some(&DeviceError::Spi(SpiError::Partial { completed: 33 })),
+//! );
+//!
 //! // Or on the pin:
-//! cs_control
-//!     .set_error(PinError::SetHigh)
+//! cs_control.set_error(PinError::SetLow);
+//! let err = spi.transfer(&mut [0x00]).unwrap_err();
+//! assert_eq!(err.device_error(), Some(&DeviceError::Pin(PinError::SetLow)));
+//! ```
+//!
+//! ## Input pins
+//!
+//! A mock input pin can return a fixed level, a sequence of levels over
+//! successive reads, or the result of a closure:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let (sck, _) = Mock::pin("SCK").without_log().init();
+//! let (mosi, _) = Mock::pin("MOSI").without_log().init();
+//! let (miso, miso_control) = Mock::input_pin("MISO")
+//!     .without_log()
+//!     .with_sequence([true, false, true, false, false, false, false, true])
+//!     .init();
+//!
+//! let mut spi = Transport::bitbang(sck, mosi, miso, NoDelay).init();
+//! assert_eq!(spi.transfer(&mut [0x00]).unwrap(), &[0xa1]);
+//! assert_eq!(miso_control.get_reads().len(), 8);
+//! ```
+//!
+//! ## Log sinks
+//!
+//! Events are printed to stdout unless a [`LogSink`] is provided. Sharing
+//! one sink keeps the events of several devices in order:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let sink = BufferSink::new();
+//! let (spi, _) = Mock::spi("MockSPI").with_sink(sink.clone()).init();
+//! let (cs, _) = Mock::pin("MockCS").with_sink(sink.clone()).init();
+//!
+//! let mut spi = Transport::hal(spi).with_cs(cs).init();
+//! spi.transfer(&mut [0x01]).unwrap();
+//!
+//! assert_eq!(sink.lines()[1], "MockCS -> low");
+//! assert_eq!(sink.lines()[2], "MockSPI -> Start transfer (1 bytes)");
+//! ```
+//!
+//! With the `log` or `tracing` features, [`LogCrateSink`](sink::LogCrateSink)
+//! and [`TracingSink`](sink::TracingSink) forward events to those crates.
+//!
+//! ## Threads
+//!
+//! Mocks and intercepts share their state with their controllers through
+//! [`Shared`], backed by [`Arc`](std::sync::Arc) and
+//! [`Mutex`](std::sync::Mutex), so devices and controllers can be moved to
+//! other threads or tasks. Generators, models, sinks and subscribers must be
+//! [`Send`] and [`Sync`]:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//! use std::thread;
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI")
+//!     .without_log()
+//!     .with_generator(|tx: &[u8]| tx.iter().map(|byte| !byte).collect())
+//!     .init();
+//! let (cs, cs_control) = Mock::pin("MockCS").without_log().init();
+//!
+//! let worker = thread::spawn(move || {
+//!     let mut spi = Transport::hal(spi).with_cs(cs).init();
+//!     spi.transfer(&mut [0x0f]).unwrap().to_vec()
+//! });
+//!
+//! assert_eq!(worker.join().unwrap(), [0xf0]);
+//! assert_eq!(spi_control.get_traffic(), [(vec![0x0f], vec![0xf0])]);
+//! assert!(cs_control.get_value());
+//! ```
+//!
+//! ## Expectations
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI")
+//!     .with_expectations([
+//!         Expectation::transfer(&[0x8f, 0x00], &[0x00, 0x33]),
+//!         Expectation::write(&[0x20, 0x01]),
+//!     ])
+//!     .init();
+//!
+//! let mut spi = Transport::hal(spi).init();
+//! assert_eq!(spi.transfer(&mut [0x8f, 0x00]).unwrap(), &[0x00, 0x33]);
+//! spi.transfer(&mut [0x20, 0x01]).unwrap();
+//! spi_control.verify().unwrap();
+//! ```
+//!
+//! With a chip select pin attached, an expectation covers a whole frame from
+//! select to deselect, such as a transaction. A transfer which does not
+//! match fails, and [`verify`](spi::mock::SpiControl::verify) returns the
+//! diff. Departures left unverified panic when the mock is dropped:
+//!
+//! ```
+//! use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI")
+//!     .without_log()
+//!     .with_expectations([
+//!         Expectation::transfer(&[0x03, 0x00, 0x00, 0x00], &[0x00, 0x00, 0xca, 0xfe]),
+//!         Expectation::write(&[0x06]),
+//!     ])
+//!     .init();
+//! let (cs, cs_control) = Mock::pin("MockCS").without_log().init();
+//! spi_control.attach_cs(&cs_control, Polarity::IdleHigh);
+//!
+//! let mut spi = Transport::hal(spi).with_cs(cs).init();
+//! let mut data = [0; 2];
+//! spi.transaction(&mut [Operation::Write(&[0x03, 0x00]), Operation::Read(&mut data)])
+//!     .unwrap();
+//! assert_eq!(data, [0xca, 0xfe]);
+//!
+//! let err = spi.transfer(&mut [0x04]).unwrap_err();
+//! assert_eq!(err.device_error(), Some(&DeviceError::Spi(SpiError::Unexpected)));
+//!
+//! let errors = spi_control.verify().unwrap_err();
+//! assert_eq!(
+//!     errors[0],
+//!     ExpectationError::Mismatch { index: 1, expected: vec![0x06], actual: vec![0x04] },
+//! );
+//! spi_control.clear_expectations();
+//! ```
+//!
+//! ## Partial transfers
+//!
+//! A deferred mock error fails a transfer part way, with
+//! [`SpiError::Partial`](spi::mock::SpiError::Partial) telling how many bytes
+//! were exchanged. A hal transport counts them in its errors when given
+//! [`SpiError::completed`](spi::mock::SpiError::completed), so
+//! [`SpiDev::transfer_resumable`](crate::SpiDev::transfer_resumable) resumes
+//! the failed chunk from the byte it failed at:
+//!
+//! ```
+//! use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+//! let (cs, _) = Mock::pin("MockCS").without_log().init();
+//! let mut spi = Transport::hal(spi)
+//!     .with_cs(cs)
+//!     .with_progress(SpiError::completed)
+//!     .init();
+//!
+//! spi_control.set_error(SpiError::Transfer).set_error_defer_bytes(50);
+//! let mut offsets = Vec::new();
+//! spi.transfer_resumable(&mut [0x00; 64], Resume::new(32, 1), |_, offset| {
+//!     offsets.push(offset);
+//!     Ok(())
+//! })
+//! .unwrap();
+//! assert_eq!(offsets, [0, 32, 50]);
+//! ```
+//!
+//! ## Chunked transfers
+//!
+//! A mock can refuse transfers longer than a peripheral's buffer, and a
+//! transport with a maximum chunk length splits longer transfers while the
+//! chip stays selected:
+//!
+//! ```
+//! use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI")
+//!     .without_log()
+//!     .with_max_transfer_len(16)
+//!     .init();
+//! let (cs, _) = Mock::pin("MockCS").without_log().init();
+//! let mut spi = Transport::hal(spi).with_cs(cs).with_max_chunk(16).init();
+//!
+//! assert_eq!(spi.max_transfer_len(), Some(16));
+//! spi.transfer(&mut [0x00; 40]).unwrap();
+//! assert_eq!(spi_control.get_transfers().len(), 3);
+//!
+//! spi_control.set_max_transfer_len(Some(8));
+//! let err = spi.transfer(&mut [0x00; 40]).unwrap_err();
+//! assert_eq!(err.device_error(), Some(&DeviceError::Spi(SpiError::TooLong)));
+//!
+//! // Errors count the bytes of the chunks before the one which failed
+//! spi_control.set_max_transfer_len(None).set_error(SpiError::Transfer);
+//! spi_control.set_error_defer_bytes(36);
+//! let err = spi.transfer(&mut [0x00; 40]).unwrap_err();
+//! assert_eq!(err.completed(), Some(32));
+//! ```
+//!
+//! ## Fault injection
+//!
+//! Mocks inject random faults from a seeded [`FaultPolicy`], so a failing
+//! run is reproduced by its seed:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let policy = FaultPolicy::new(42).with_bit_flip(0.5).with_stuck_high(0x80);
+//! let mut runs = Vec::new();
+//!
+//! for _ in 0..2 {
+//!     let (spi, spi_control) = Mock::spi("MockSPI").without_log().with_faults(policy).init();
+//!     let mut spi = Transport::hal(spi).init();
+//!
+//!     let rx = spi.transfer(&mut [0x00; 16]).unwrap().to_vec();
+//!     assert!(rx.iter().all(|word| word & 0x80 != 0));
+//!     runs.push((rx, spi_control.get_fault_counts()));
+//! }
+//!
+//! assert_eq!(runs[0], runs[1]);
+//! assert!(runs[0].1.bit_flips > 0);
+//! ```
+//!
+//! ## Events
+//!
+//! An SPI intercept sends an [`SpiEvent`] for every operation to its
+//! subscribers. An [`EventRecorder`] keeps them for queries:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let (spi, _) = Mock::spi("MockSPI").without_log().init();
+//! let (cs, _) = Mock::pin("MockCS").without_log().init();
+//!
+//! let recorder = EventRecorder::new();
+//! let mut spi = Intercept::spi("Chip")
+//!     .without_log()
+//!     .with_subscriber(recorder.clone())
+//!     .init(Transport::hal(spi).with_cs(cs).init());
+//!
+//! spi.select().unwrap();
+//! spi.raw_transfer(&mut [0x9f, 0x00]).unwrap();
+//! spi.deselect().unwrap();
+//! spi.transfer(&mut [0x01]).unwrap();
+//!
+//! assert!(recorder.happened_before(
+//!     |event| event.operation == SpiOperation::RawTransfer && event.selected,
+//!     |event| event.operation == SpiOperation::Transfer,
+//! ));
+//! assert_eq!(recorder.first(|event| event.bytes == 2).unwrap().sequence, 1);
+//! ```
+//!
+//! ## Traffic assertions
+//!
+//! [`assert_sent!`](crate::assert_sent) and
+//! [`assert_transfer_sequence!`](crate::assert_transfer_sequence) match the
+//! Tx bytes of mock SPI or a [`Capture`] against byte patterns, and print
+//! annotated hexdumps when they differ:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+//! let mut spi = Transport::hal(spi).init();
+//! spi.transfer(&mut [0x06]).unwrap();
+//! spi.transfer(&mut [0x02, 0x00, 0x10, 0x00, 0xde, 0xad]).unwrap();
+//!
+//! assert_sent!(spi_control, [0x02, any_n(3), rest()]);
+//! assert_transfer_sequence!(spi_control, [
+//!     [0x06],
+//!     [mask(0xf0, 0x00), any().repeat(3..=8)],
+//! ]);
+//! ```
+//!
+//! ## Waveforms
+//!
+//! A [`Capture`] of intercepted or mock activity can be exported as a Value
+//! Change Dump, reconstructing SCLK, MOSI and MISO from the transfers:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let capture = Capture::new();
+//! let (mut spi, _) = Mock::spi("MockSPI").without_log().init();
+//! let (mut cs, _) = Mock::pin("MockCS").without_log().init();
+//! spi.set_capture(Some(capture.clone()));
+//! cs.set_capture(Some(capture.clone()));
+//!
+//! let mut spi = Transport::hal(spi).with_cs(cs).init();
+//! spi.transfer(&mut [0x9f, 0x00]).unwrap();
+//!
+//! let mut vcd = Vec::new();
+//! capture.write_vcd(&mut vcd, VcdOptions::new().with_clock_speed(8_000_000)).unwrap();
+//! assert!(String::from_utf8(vcd).unwrap().contains("$var wire 1 % MockCS $end"));
+//! ```
+//!
+//! ## Mock buses
+//!
+//! A mock bus routes each transfer to the chip whose mock chip select pin is
+//! active. Transfers selecting no chip, or several, fail and are recorded as
+//! [`BusViolation`]s:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let (spi, bus_control) = Mock::bus("Bus").without_log().init();
+//! let (adc_cs, _) = bus_control.device("ADC", Polarity::IdleHigh, |tx: &[u8]| vec![0xad; tx.len()]);
+//! let (flash_cs, _) = bus_control.device("Flash", Polarity::IdleLow, |tx: &[u8]| vec![0xf1; tx.len()]);
+//!
+//! let bus = SharedBus::ref_cell(Transport::hal(spi).init());
+//! let mut adc = bus.device(adc_cs).init();
+//! let mut flash = bus.device(flash_cs).with_polarity(Polarity::IdleLow).init();
+//!
+//! assert_eq!(adc.transfer(&mut [0x00]).unwrap(), &[0xad]);
+//! assert_eq!(flash.transfer(&mut [0x00]).unwrap(), &[0xf1]);
+//! bus_control.verify().unwrap();
+//! ```
+//!
+//! ## Chip select timing
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//!
+//! let timing = Timing::new(10, 5, 20);
+//! let (spi, spi_control) = Mock::spi("MockSPI").init();
+//! let (cs, cs_control) = Mock::pin("MockCS").init();
+//!
+//! let mut spi = Transport::hal(spi)
+//!     .with_cs(cs)
+//!     .with_timing(MockDelay, timing)
+//!     .init();
+//!
+//! spi.transfer(&mut [0x01, 0x02]).unwrap();
+//! spi.transfer(&mut [0x03, 0x04]).unwrap();
+//! check_timing(&cs_control, &spi_control, Polarity::IdleHigh, timing).unwrap();
+//! ```
+//!
+//! ## Virtual time
+//!
+//! Mocks sharing a [`VirtualClock`] advance it instead of sleeping, and
+//! record times from it. Together with a [`VirtualDelay`], timing checks run
+//! instantly and deterministically:
+//!
+//! ```
+//! use rpio_utils::{*, dev::*};
+//! use std::time::Duration;
+//!
+//! let clock = VirtualClock::new();
+//! let timing = Timing::new(10, 5, 20);
+//! let (spi, spi_control) = Mock::spi("MockSPI")
+//!     .with_clock(&clock)
+//!     .with_byte_delay(Duration::from_secs(1))
+//!     .init();
+//! let (cs, cs_control) = Mock::pin("MockCS").with_clock(&clock).init();
+//!
+//! let mut spi = Transport::hal(spi)
+//!     .with_cs(cs)
+//!     .with_timing(VirtualDelay::new(&clock), timing)
+//!     .init();
+//!
+//! spi.transfer(&mut [0x01, 0x02]).unwrap();
+//! spi.transfer(&mut [0x03, 0x04]).unwrap();
+//! check_timing(&cs_control, &spi_control, Polarity::IdleHigh, timing).unwrap();
+//!
+//! // Four bytes at a second each, plus chip select delays, without waiting
+//! assert!(clock.elapsed() > Duration::from_secs(4));
+//! ```
+//!
+//! With the `eh1` feature, mocks and intercepts also implement the
+//! [`embedded_hal`] 1.0 traits. With the `async` feature, mock SPI implements
+//! the `embedded-hal-async` bus, so it can back an async transport. A
+//! [`VirtualDelay`] serves its delays:
+//!
+//! ```
+//! # #[cfg(feature = "async")] {
+//! use rpio_utils::{*, dev::*};
+//! use std::{future::Future, pin::pin, task::{Context, Poll, Waker}, time::Duration};
+//!
+//! // Mocks never pend, so polling once completes the future
+//! fn block_on<F: Future>(future: F) -> F::Output {
+//!     let mut context = Context::from_waker(Waker::noop());
+//!     match pin!(future).poll(&mut context) {
+//!         Poll::Ready(output) => output,
+//!         Poll::Pending => unreachable!(),
+//!     }
+//! }
+//!
+//! let clock = VirtualClock::new();
+//! let (spi, spi_control) = Mock::spi("MockSPI").init();
+//! let (cs, _cs_control) = Mock::pin("MockCS").init();
+//!
+//! let mut spi = Transport::hal_async(spi)
+//!     .with_cs(cs)
+//!     .with_delay(VirtualDelay::new(&clock))
+//!     .init();
+//!
+//! let mut response = [0; 2];
+//! block_on(spi.transaction(&mut [
+//!     Operation::Write(&[0x03, 0x00]),
+//!     Operation::DelayUs(250),
+//!     Operation::Read(&mut response),
+//! ]))
+//! .unwrap();
+//!
+//! assert_eq!(clock.elapsed(), Duration::from_micros(250));
+//! assert_eq!(spi_control.get_traffic().len(), 2);
+//!
+//! // Without a delay, the transaction cannot wait
+//! let (spi, _) = Mock::spi("MockSPI").init();
+//! let (cs, _) = Mock::pin("MockCS").init();
+//! let mut spi = Transport::hal_async(spi).with_cs(cs).init();
+//! let err = block_on(spi.transaction(&mut [Operation::DelayUs(250)])).unwrap_err();
+//! assert_eq!(err, ErrorKind::NotImplemented);
+//! # }
 //! ```
 
 #[macro_use]
 mod builder;
 
+pub mod bus;
+pub mod capture;
+pub mod cell;
+pub mod clock;
+pub mod event;
+pub mod fault;
+pub mod input;
+pub mod matcher;
 pub mod output;
+pub mod register;
+pub mod sink;
 pub mod spi;
+pub mod timing;
+pub mod vcd;
 
 pub use {
     builder::{Intercept, Mock},
+    bus::BusViolation,
+    capture::{Capture, CaptureEvent},
+    cell::Shared,
+    clock::VirtualClock,
+    event::{EventRecorder, EventSubscriber, SpiEvent, SpiOperation},
+    fault::{FaultCounts, FaultPolicy},
+    input::mock::InputError,
+    matcher::{Pattern, Traffic, TrafficMismatch},
     output::mock::PinError,
+    register::RegisterModel,
+    sink::{BufferSink, LogSink, StdoutSink, WriteSink},
+    spi::{
+        expect::{Expectation, ExpectationError},
+        model::SpiDeviceModel,
+    },
+    timing::{check_timing, MockDelay, TimingViolation, VirtualDelay},
+    vcd::VcdOptions,
 };
+
+#[cfg(feature = "log")]
+pub use sink::LogCrateSink;
+
+#[cfg(feature = "tracing")]
+pub use sink::TracingSink;
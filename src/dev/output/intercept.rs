@@ -1,22 +1,60 @@
-use embedded_hal::digital::v2::OutputPin;
-use std::{borrow::ToOwned, cell::RefCell, println, rc::Rc, string::String};
+use crate::dev::{
+    capture::Capture,
+    cell::Shared,
+    sink::{default_sink, LogSink, SharedSink},
+};
+use embedded_hal::digital::v2::{OutputPin, StatefulOutputPin, ToggleableOutputPin};
+use std::{borrow::ToOwned, format, string::String, sync::Arc};
 
 /// Intercepts [`OutputPin`](OutputPin), providing logging.
 pub struct Pin<P: OutputPin> {
     name: String,
     pin: P,
-    opts: Rc<RefCell<PinOpts>>,
+    opts: Shared<PinOpts>,
+    capture: Option<Capture>,
+    sink: SharedSink,
 }
 
 impl<P: OutputPin> Pin<P> {
-    pub fn new(name: String, pin: P, opts: Rc<RefCell<PinOpts>>) -> Self {
-        Self { name, pin, opts }
+    pub fn new(name: String, pin: P, opts: Shared<PinOpts>) -> Self {
+        Self {
+            name,
+            pin,
+            opts,
+            capture: None,
+            sink: default_sink(),
+        }
+    }
+
+    /// Send events to the sink instead of stdout.
+    pub fn set_sink<L: LogSink + 'static>(&mut self, sink: L) {
+        self.sink = Arc::new(sink);
+    }
+
+    /// Record edges into the capture.
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.capture = capture;
     }
 
     /// Set whether events are printed to stdout.
     pub fn set_log(&mut self, log: bool) {
         self.opts.borrow_mut().log = log;
     }
+
+    fn log_set(&self, level: &str, ok: bool) {
+        if let Some(capture) = self.capture.as_ref().filter(|_| ok) {
+            capture.record_edge(&self.name, level == "high");
+        }
+
+        if self.opts.borrow().log {
+            match ok {
+                true => self.sink.log(&self.name, level),
+                false => self
+                    .sink
+                    .log(&self.name, &format!("Error (not set {})", level)),
+            };
+        }
+    }
 }
 
 impl<P: OutputPin> OutputPin for Pin<P> {
@@ -24,28 +62,73 @@ impl<P: OutputPin> OutputPin for Pin<P> {
 
     fn set_high(&mut self) -> Result<(), Self::Error> {
         let result = self.pin.set_high();
+        self.log_set("high", result.is_ok());
+        result
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let result = self.pin.set_low();
+        self.log_set("low", result.is_ok());
+        result
+    }
+}
+
+impl<P: StatefulOutputPin> StatefulOutputPin for Pin<P> {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.pin.is_set_high()
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.pin.is_set_low()
+    }
+}
+
+/// Toggles are logged, but not recorded as edges in a capture, since the new
+/// level is not known.
+impl<P: OutputPin + ToggleableOutputPin> ToggleableOutputPin for Pin<P> {
+    type Error = <P as ToggleableOutputPin>::Error;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        let result = self.pin.toggle();
         if self.opts.borrow().log {
             match result {
-                Ok(_) => println!("{} -> high", self.name),
-                Err(_) => println!("{} -> Error (not set high)", self.name),
+                Ok(_) => self.sink.log(&self.name, "toggled"),
+                Err(_) => self.sink.log(&self.name, "Error (not toggled)"),
             };
         }
 
         result
     }
+}
+
+#[cfg(feature = "eh1")]
+impl<P: OutputPin + _eh1::digital::ErrorType> _eh1::digital::ErrorType for Pin<P> {
+    type Error = <P as _eh1::digital::ErrorType>::Error;
+}
 
+#[cfg(feature = "eh1")]
+impl<P: OutputPin + _eh1::digital::OutputPin> _eh1::digital::OutputPin for Pin<P> {
     fn set_low(&mut self) -> Result<(), Self::Error> {
-        if self.opts.borrow().log {
-            self.pin
-                .set_low()
-                .map(|_| println!("{} -> low", self.name))
-                .map_err(|err| {
-                    println!("{} -> Error (not set low)", self.name);
-                    err
-                })
-        } else {
-            self.pin.set_low()
-        }
+        let result = _eh1::digital::OutputPin::set_low(&mut self.pin);
+        self.log_set("low", result.is_ok());
+        result
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let result = _eh1::digital::OutputPin::set_high(&mut self.pin);
+        self.log_set("high", result.is_ok());
+        result
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<P: OutputPin + _eh1::digital::StatefulOutputPin> _eh1::digital::StatefulOutputPin for Pin<P> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        _eh1::digital::StatefulOutputPin::is_set_high(&mut self.pin)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        _eh1::digital::StatefulOutputPin::is_set_low(&mut self.pin)
     }
 }
 
@@ -61,10 +144,25 @@ impl PinOpts {
     }
 }
 
-builder!(InterceptBuilder<PinOpts> + Clone, Debug {});
+builder!(InterceptBuilder<PinOpts> + Clone, Debug {
+    capture: Option<Capture> = None,
+});
 
 impl InterceptBuilder {
+    /// Record edges into the capture.
+    pub fn with_capture(mut self, capture: &Capture) -> Self {
+        self.capture = Some(capture.clone());
+        self
+    }
+
     pub fn init<P: OutputPin>(self, pin: P) -> Pin<P> {
-        Pin::new(self.name, pin, Rc::new(RefCell::new(self.opts)))
+        let mut pin = Pin::new(self.name, pin, Shared::new(self.opts));
+        pin.set_capture(self.capture);
+
+        if let Some(sink) = self.sink {
+            pin.set_sink(sink);
+        }
+
+        pin
     }
 }
@@ -1,15 +1,30 @@
-use super::intercept::{Pin, PinOpts};
+use super::{
+    super::{
+        cell::Shared,
+        clock::{Clock, VirtualClock},
+        fault::{FaultCounts, FaultInjector, FaultPolicy},
+    },
+    intercept::{Pin, PinOpts},
+};
 use crate::OutputPin;
-use std::{borrow::ToOwned, cell::RefCell, rc::Rc, string::String, thread, time::Duration};
+use embedded_hal::digital::v2::{StatefulOutputPin, ToggleableOutputPin};
+use std::{
+    borrow::ToOwned,
+    boxed::Box,
+    fmt,
+    string::String,
+    time::{Duration, Instant},
+    vec::Vec,
+};
 
 /// State interface for mock output pin.
 #[derive(Debug)]
 pub struct MockPin {
-    dev: Rc<RefCell<MockPinDevice>>,
+    dev: Shared<MockPinDevice>,
 }
 
 impl MockPin {
-    fn new(dev: Rc<RefCell<MockPinDevice>>) -> Self {
+    fn new(dev: Shared<MockPinDevice>) -> Self {
         Self { dev }
     }
 }
@@ -30,6 +45,55 @@ impl OutputPin for MockPin {
     }
 }
 
+impl StatefulOutputPin for MockPin {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.dev.borrow().value)
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.dev.borrow().value)
+    }
+}
+
+impl ToggleableOutputPin for MockPin {
+    type Error = PinError;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        let value = self.dev.borrow().value;
+        match value {
+            true => OutputPin::set_low(self),
+            false => OutputPin::set_high(self),
+        }
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::ErrorType for MockPin {
+    type Error = PinError;
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_low(self)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_high(self)
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::digital::StatefulOutputPin for MockPin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        StatefulOutputPin::is_set_high(self)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        StatefulOutputPin::is_set_low(self)
+    }
+}
+
 /// An enum of mock output pin errors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PinError {
@@ -37,34 +101,76 @@ pub enum PinError {
     SetLow,
 }
 
+#[cfg(feature = "eh1")]
+impl _eh1::digital::Error for PinError {
+    fn kind(&self) -> _eh1::digital::ErrorKind {
+        _eh1::digital::ErrorKind::Other
+    }
+}
+
+/// Called with the new value of a mock output pin after each change. An
+/// error fails the change, although the value is kept.
+pub(crate) trait EdgeListener: FnMut(bool) -> Result<(), ()> + Send + Sync {}
+
+impl<F: FnMut(bool) -> Result<(), ()> + Send + Sync> EdgeListener for F {}
+
+impl fmt::Debug for dyn EdgeListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Function (Edge Listener)")
+    }
+}
+
 /// Holds the underlying state shared by [MockPin] and [PinControl].
 #[derive(Debug)]
 pub struct MockPinDevice {
-    opts: Rc<RefCell<PinOpts>>,
+    opts: Shared<PinOpts>,
     value: bool,
     delay: Option<Duration>,
     error: Option<PinError>,
+    edges: Vec<(Instant, bool)>,
+    clock: Clock,
+    listener: Option<Box<dyn EdgeListener>>,
+    faults: Option<FaultInjector>,
 }
 
 impl MockPinDevice {
-    fn new(opts: Rc<RefCell<PinOpts>>) -> Self {
+    fn new(opts: Shared<PinOpts>) -> Self {
         Self {
             opts,
             value: true,
             delay: None,
             error: None,
+            edges: Vec::new(),
+            clock: Clock::Real,
+            listener: None,
+            faults: None,
         }
     }
 
     fn set_value_unless(&mut self, value: bool, unless: PinError) -> Result<(), PinError> {
         if let Some(delay) = self.delay {
-            thread::sleep(delay);
+            self.clock.sleep(delay);
         }
 
         match self.error {
             Some(error) if error == unless => Err(self.error.take().unwrap()),
             _ => {
+                let changed = self.value != value;
+                let fault = changed && self.faults.as_mut().is_some_and(FaultInjector::fail_pin);
+                if fault {
+                    return Err(unless);
+                }
+
                 self.value = value;
+
+                if changed {
+                    self.edges.push((self.clock.now(), value));
+
+                    if let Some(listener) = &mut self.listener {
+                        listener(value).or(Err(unless))?;
+                    }
+                }
+
                 Ok(())
             }
         }
@@ -74,11 +180,11 @@ impl MockPinDevice {
 /// Developer controls for mock output pin.
 #[derive(Debug)]
 pub struct PinControl {
-    pin: Rc<RefCell<MockPinDevice>>,
+    pin: Shared<MockPinDevice>,
 }
 
 impl PinControl {
-    fn new(pin: Rc<RefCell<MockPinDevice>>) -> Self {
+    fn new(pin: Shared<MockPinDevice>) -> Self {
         Self { pin }
     }
 
@@ -116,10 +222,53 @@ impl PinControl {
     pub fn get_value(&self) -> bool {
         self.pin.borrow().value
     }
+
+    /// Get the time and new value of each change of the pin's value.
+    pub fn get_edges(&self) -> Vec<(Instant, bool)> {
+        self.pin.borrow().edges.clone()
+    }
+
+    /// Forget the recorded changes of the pin's value.
+    pub fn clear_edges(&self) -> &Self {
+        self.pin.borrow_mut().edges.clear();
+        self
+    }
+
+    /// Inject random faults into changes of the pin's value, replacing any
+    /// previous policy and its counts.
+    pub fn set_faults(&self, policy: FaultPolicy) -> &Self {
+        self.pin.borrow_mut().faults = Some(FaultInjector::new(policy));
+        self
+    }
+
+    /// Stop injecting faults, and forget their counts.
+    pub fn clear_faults(&self) -> &Self {
+        self.pin.borrow_mut().faults = None;
+        self
+    }
+
+    /// Get the number of faults injected since the policy was set.
+    pub fn get_fault_counts(&self) -> FaultCounts {
+        self.pin
+            .borrow()
+            .faults
+            .as_ref()
+            .map(FaultInjector::counts)
+            .unwrap_or_default()
+    }
+
+    /// Call the listener after each change of the pin's value.
+    pub(crate) fn set_listener(&self, listener: Box<dyn EdgeListener>) -> &Self {
+        self.pin.borrow_mut().listener = Some(listener);
+        self
+    }
 }
 
 builder!(MockBuilder<PinOpts> + Clone, Debug {
     delay: Option<Duration> = None,
+    clock: Clock = Clock::Real,
+    value: bool = true,
+    faults: Option<FaultPolicy> = None,
 });
 
 impl MockBuilder {
@@ -129,17 +278,44 @@ impl MockBuilder {
         self
     }
 
+    /// Start the pin at the given value instead of high.
+    pub fn with_value(mut self, value: bool) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Inject random faults into changes of the pin's value. See
+    /// [`FaultPolicy`].
+    pub fn with_faults(mut self, policy: FaultPolicy) -> Self {
+        self.faults = Some(policy);
+        self
+    }
+
+    /// Advance the virtual clock for delays instead of sleeping, and record
+    /// times from it.
+    pub fn with_clock(mut self, clock: &VirtualClock) -> Self {
+        self.clock = Clock::Virtual(clock.clone());
+        self
+    }
+
     /// Create the mock output pin and controller.
     pub fn init(self) -> (Pin<MockPin>, PinControl) {
-        let opts = Rc::new(RefCell::new(self.opts));
-        let dev = Rc::new(RefCell::new(MockPinDevice::new(opts.clone())));
+        let opts = Shared::new(self.opts);
+        let dev = Shared::new(MockPinDevice::new(opts.clone()));
+        dev.borrow_mut().clock = self.clock;
+        dev.borrow_mut().value = self.value;
+        dev.borrow_mut().faults = self.faults.map(FaultInjector::new);
         let control = PinControl::new(dev.clone());
-        let pin = Pin::new(self.name, MockPin::new(dev), opts);
+        let mut pin = Pin::new(self.name, MockPin::new(dev), opts);
 
         if let Some(delay) = self.delay {
             control.set_delay(delay);
         }
 
+        if let Some(sink) = self.sink {
+            pin.set_sink(sink);
+        }
+
         (pin, control)
     }
 }
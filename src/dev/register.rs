@@ -0,0 +1,170 @@
+use super::cell::Shared;
+use super::spi::{
+    mock::{BoxedGenerator, SpiError},
+    model::SpiDeviceModel,
+};
+use crate::register::{Access, Layout, Register, MAX_REGISTER_BYTES};
+use std::{boxed::Box, collections::BTreeMap, vec::Vec};
+
+/// A mock chip holding registers, which answers the frames sent by a
+/// [`RegisterInterface`](crate::RegisterInterface) with the same [`Layout`].
+///
+/// Usually created by the `model()` function of a
+/// [`register_map!`](crate::register_map), then used as the
+/// [`SpiDeviceModel`] of mock SPI. A frame runs from select to deselect, so
+/// without a chip select pin [attached](super::spi::mock::SpiControl::attach_cs)
+/// each frame must arrive in a single transfer, as single register accesses
+/// do. Reads of unknown or write-only registers return 0, and writes to
+/// unknown or read-only registers are ignored. Multi-register frames access
+/// consecutive addresses.
+///
+/// ```ignore
+/// let model = accel::model();
+/// model.set_reg(accel::WhoAmI(0x33));
+///
+/// let (spi, spi_control) = Mock::spi("Accel").with_model(model.clone()).init();
+///
+/// let mut regs = accel::interface(Transport::hal(spi).init());
+/// assert_eq!(regs.read::<accel::WhoAmI>()?.id(), 0x33);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegisterModel {
+    state: Shared<ModelState>,
+}
+
+#[derive(Debug)]
+struct ModelState {
+    layout: Layout,
+    registers: BTreeMap<u32, (Access, u32)>,
+    frame: Vec<u8>,
+}
+
+impl ModelState {
+    /// Answer the next byte of the frame, updating a written register once
+    /// all of its bytes have arrived.
+    fn exchange(&mut self, tx: u8) -> u8 {
+        let layout = self.layout;
+        self.frame.push(tx);
+
+        let Some((address, read)) = layout.parse_header(&self.frame) else {
+            return 0x00;
+        };
+
+        let start = match read {
+            true => layout.address_bytes + layout.dummy_bytes,
+            false => layout.address_bytes,
+        };
+
+        let Some(offset) = (self.frame.len() - 1).checked_sub(start) else {
+            return 0x00;
+        };
+
+        let address = address.wrapping_add((offset / layout.register_bytes) as u32);
+        let byte = offset % layout.register_bytes;
+
+        match (read, self.registers.get_mut(&address)) {
+            (true, Some((Access::ReadOnly | Access::ReadWrite, value))) => {
+                let mut words = [0x00; MAX_REGISTER_BYTES];
+                layout.encode(*value, &mut words[..layout.register_bytes]);
+                words[byte]
+            }
+            (false, Some((Access::WriteOnly | Access::ReadWrite, value)))
+                if byte + 1 == layout.register_bytes =>
+            {
+                *value = layout.decode(&self.frame[self.frame.len() - layout.register_bytes..]);
+                0x00
+            }
+            _ => 0x00,
+        }
+    }
+}
+
+impl RegisterModel {
+    /// Create a model without registers.
+    pub fn new(layout: Layout) -> Self {
+        Self {
+            state: Shared::new(ModelState {
+                layout,
+                registers: BTreeMap::new(),
+                frame: Vec::new(),
+            }),
+        }
+    }
+
+    /// Add a register starting at 0.
+    pub fn with_register<R: Register>(self) -> Self {
+        self.add_register(R::ADDRESS, R::ACCESS, 0);
+        self
+    }
+
+    /// Add a register by address.
+    pub fn add_register(&self, address: u32, access: Access, value: u32) -> &Self {
+        self.state
+            .borrow_mut()
+            .registers
+            .insert(address, (access, value));
+        self
+    }
+
+    /// Get the value of a register, regardless of its access.
+    pub fn get(&self, address: u32) -> Option<u32> {
+        self.state
+            .borrow()
+            .registers
+            .get(&address)
+            .map(|(_, value)| *value)
+    }
+
+    /// Set the value of a known register, regardless of its access.
+    pub fn set(&self, address: u32, value: u32) -> &Self {
+        if let Some((_, current)) = self.state.borrow_mut().registers.get_mut(&address) {
+            *current = value;
+        }
+        self
+    }
+
+    /// Get the value of a register declared with
+    /// [`register_map!`](crate::register_map).
+    pub fn get_reg<R: Register>(&self) -> R {
+        R::from_bits(self.get(R::ADDRESS).unwrap_or_default())
+    }
+
+    /// Set the value of a register declared with
+    /// [`register_map!`](crate::register_map).
+    pub fn set_reg<R: Register>(&self, value: R) -> &Self {
+        self.set(R::ADDRESS, value.bits())
+    }
+
+    /// Answer a whole frame, updating any written registers.
+    pub fn respond(&self, tx: &[u8]) -> Vec<u8> {
+        let mut state = self.state.borrow_mut();
+        state.frame.clear();
+
+        let rx = tx.iter().map(|word| state.exchange(*word)).collect();
+        state.frame.clear();
+        rx
+    }
+
+    /// Create a generator for mock SPI which answers each transfer as a
+    /// frame.
+    pub fn generator(&self) -> BoxedGenerator {
+        let model = self.clone();
+        Box::new(move |tx: &[u8]| model.respond(tx))
+    }
+}
+
+impl SpiDeviceModel for RegisterModel {
+    fn on_select(&mut self) -> Result<(), SpiError> {
+        self.state.borrow_mut().frame.clear();
+        Ok(())
+    }
+
+    fn on_deselect(&mut self) -> Result<(), SpiError> {
+        self.state.borrow_mut().frame.clear();
+        Ok(())
+    }
+
+    fn exchange_byte(&mut self, tx: u8) -> Result<u8, SpiError> {
+        Ok(self.state.borrow_mut().exchange(tx))
+    }
+}
@@ -0,0 +1,161 @@
+use super::cell::Shared;
+use std::{
+    fmt, format,
+    io::Write,
+    println,
+    string::String,
+    sync::{Arc, Mutex},
+    vec::Vec,
+};
+
+/// Receives the events printed by intercepts and mocks. Each line names the
+/// device it came from.
+///
+/// A sink can be shared by several devices (see [`with_sink`]), so their
+/// events are received in the order they happened.
+///
+/// [`with_sink`]: super::spi::intercept::InterceptBuilder::with_sink
+pub trait LogSink: Send + Sync {
+    /// Receive a line logged by the named device.
+    fn log(&self, name: &str, message: &str);
+}
+
+impl fmt::Debug for dyn LogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LogSink")
+    }
+}
+
+impl<L: LogSink + ?Sized> LogSink for Arc<L> {
+    fn log(&self, name: &str, message: &str) {
+        (**self).log(name, message)
+    }
+}
+
+/// A cloneable handle to a [`LogSink`], used by devices.
+pub(crate) type SharedSink = Arc<dyn LogSink>;
+
+/// The default sink for every device.
+pub(crate) fn default_sink() -> SharedSink {
+    Arc::new(StdoutSink)
+}
+
+/// Prints to stdout.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl LogSink for StdoutSink {
+    fn log(&self, name: &str, message: &str) {
+        println!("{} -> {}", name, message);
+    }
+}
+
+/// Writes each line to an [`std::io::Write`], ignoring write errors.
+#[derive(Debug)]
+pub struct WriteSink<W: Write> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write> WriteSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    /// Release the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl<W: Write + Send + Sync> LogSink for WriteSink<W> {
+    fn log(&self, name: &str, message: &str) {
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        writeln!(writer, "{} -> {}", name, message).ok();
+    }
+}
+
+/// Keeps each line in memory, so tests can assert on them. Clones share the
+/// same lines.
+#[derive(Debug, Clone, Default)]
+pub struct BufferSink {
+    lines: Shared<Vec<String>>,
+}
+
+impl BufferSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the lines received so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.borrow().clone()
+    }
+
+    /// Get the lines received so far, joined by newlines.
+    pub fn contents(&self) -> String {
+        self.lines.borrow().join("\n")
+    }
+
+    /// Forget the lines received so far.
+    pub fn clear(&self) -> &Self {
+        self.lines.borrow_mut().clear();
+        self
+    }
+}
+
+impl LogSink for BufferSink {
+    fn log(&self, name: &str, message: &str) {
+        self.lines
+            .borrow_mut()
+            .push(format!("{} -> {}", name, message));
+    }
+}
+
+/// Forwards each line to the [`log`](_log) crate at the configured level,
+/// with the device name as the target. Requires the `log` feature.
+#[cfg(feature = "log")]
+#[derive(Debug, Clone, Copy)]
+pub struct LogCrateSink {
+    level: _log::Level,
+}
+
+#[cfg(feature = "log")]
+impl LogCrateSink {
+    pub fn new(level: _log::Level) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "log")]
+impl Default for LogCrateSink {
+    fn default() -> Self {
+        Self::new(_log::Level::Debug)
+    }
+}
+
+#[cfg(feature = "log")]
+impl LogSink for LogCrateSink {
+    fn log(&self, name: &str, message: &str) {
+        _log::log!(target: name, self.level, "{}", message);
+    }
+}
+
+/// Forwards each line to the [`tracing`](_tracing) crate as a debug event,
+/// with the device name as a field. Requires the `tracing` feature.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+#[cfg(feature = "tracing")]
+impl LogSink for TracingSink {
+    fn log(&self, name: &str, message: &str) {
+        _tracing::debug!(device = name, "{}", message);
+    }
+}
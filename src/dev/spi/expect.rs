@@ -0,0 +1,180 @@
+use super::mock::SpiError;
+use std::{fmt, vec::Vec};
+
+/// A frame which mock SPI expects next, along with its scripted response.
+///
+/// With a chip select pin [attached](super::mock::SpiControl::attach_cs), a
+/// frame is every byte exchanged from select to deselect, so one
+/// expectation covers a whole transaction however it is split into
+/// transfers. Otherwise each transfer is a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expectation {
+    tx: Vec<u8>,
+    rx: Vec<u8>,
+    error: Option<SpiError>,
+}
+
+impl Expectation {
+    /// Expect exactly these Tx bytes and respond with these Rx bytes. Rx is
+    /// padded with `0x00` or truncated to the length of the frame.
+    pub fn transfer(tx: &[u8], rx: &[u8]) -> Self {
+        Self {
+            tx: tx.to_vec(),
+            rx: rx.to_vec(),
+            error: None,
+        }
+    }
+
+    /// Expect exactly these Tx bytes, responding with `0x00` bytes.
+    pub fn write(tx: &[u8]) -> Self {
+        Self::transfer(tx, &[])
+    }
+
+    /// Fail the transfer which completes the frame with this error, once
+    /// the Tx bytes have matched.
+    pub fn with_error(mut self, error: SpiError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    /// The expected Tx bytes.
+    pub fn tx(&self) -> &[u8] {
+        &self.tx
+    }
+
+    /// The scripted Rx bytes.
+    pub fn rx(&self) -> &[u8] {
+        &self.rx
+    }
+
+    /// The injected error (if set).
+    pub fn error(&self) -> Option<SpiError> {
+        self.error
+    }
+
+    fn mismatch(&self, index: usize, actual: &[u8]) -> ExpectationError {
+        ExpectationError::Mismatch {
+            index,
+            expected: self.tx.clone(),
+            actual: actual.to_vec(),
+        }
+    }
+}
+
+/// A frame in progress, matched against the expectation which was the
+/// `index`th to be set, or against none once all were consumed.
+#[derive(Debug)]
+pub(crate) struct Frame {
+    index: usize,
+    expectation: Option<Expectation>,
+    tx: Vec<u8>,
+    failed: bool,
+}
+
+impl Frame {
+    pub(crate) fn new(index: usize, expectation: Option<Expectation>) -> Self {
+        Self {
+            index,
+            expectation,
+            tx: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Match the Tx bytes of the next transfer of the frame, returning the
+    /// scripted Rx bytes. Once the frame departs from its expectation, the
+    /// difference is recorded and its transfers fail with
+    /// [`SpiError::Unexpected`].
+    pub(crate) fn exchange(
+        &mut self,
+        tx: &[u8],
+        mismatches: &mut Vec<ExpectationError>,
+    ) -> Result<Vec<u8>, SpiError> {
+        let offset = self.tx.len();
+        self.tx.extend_from_slice(tx);
+
+        if self.failed {
+            return Err(SpiError::Unexpected);
+        }
+
+        let Some(expectation) = &self.expectation else {
+            self.failed = true;
+            mismatches.push(ExpectationError::Unexpected {
+                actual: self.tx.clone(),
+            });
+            return Err(SpiError::Unexpected);
+        };
+
+        if !expectation.tx.starts_with(&self.tx) {
+            self.failed = true;
+            mismatches.push(expectation.mismatch(self.index, &self.tx));
+            return Err(SpiError::Unexpected);
+        }
+
+        if let (true, Some(error)) = (self.tx.len() == expectation.tx.len(), expectation.error) {
+            return Err(error);
+        }
+
+        Ok((offset..self.tx.len())
+            .map(|index| *expectation.rx.get(index).unwrap_or(&0x00))
+            .collect())
+    }
+
+    /// End the frame, recording a mismatch if it sent fewer bytes than
+    /// expected.
+    pub(crate) fn end(self, mismatches: &mut Vec<ExpectationError>) {
+        if let (false, Some(expectation)) = (self.failed, &self.expectation) {
+            if self.tx.len() < expectation.tx.len() {
+                mismatches.push(expectation.mismatch(self.index, &self.tx));
+            }
+        }
+    }
+}
+
+/// Describes how mock SPI traffic departed from its expectations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectationError {
+    /// A frame did not send the expected Tx bytes.
+    Mismatch {
+        index: usize,
+        expected: Vec<u8>,
+        actual: Vec<u8>,
+    },
+    /// A frame started after all expectations were consumed.
+    Unexpected { actual: Vec<u8> },
+    /// Expectations were left unconsumed.
+    Unconsumed { remaining: Vec<Expectation> },
+}
+
+impl fmt::Display for ExpectationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpectationError::Mismatch {
+                index,
+                expected,
+                actual,
+            } => {
+                let first = expected
+                    .iter()
+                    .zip(actual)
+                    .position(|(expected, actual)| expected != actual)
+                    .unwrap_or(expected.len().min(actual.len()));
+
+                writeln!(f, "Expectation {}: Tx differs at byte {}", index, first)?;
+                writeln!(f, "  expected: {:02X?}", expected)?;
+                write!(f, "    actual: {:02X?}", actual)
+            }
+            ExpectationError::Unexpected { actual } => {
+                write!(f, "Unexpected frame: {:02X?}", actual)
+            }
+            ExpectationError::Unconsumed { remaining } => {
+                write!(f, "{} expectation(s) left unconsumed", remaining.len())?;
+                remaining
+                    .iter()
+                    .try_for_each(|expectation| write!(f, "\n  Tx: {:02X?}", expectation.tx))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExpectationError {}
@@ -1,17 +1,93 @@
-use crate::{ChipSelect, ClockSpeed, SpiDev, Transfer};
-use std::{borrow::ToOwned, cell::RefCell, format, println, rc::Rc, string::String, vec::Vec};
+#[cfg(feature = "async")]
+use crate::AsyncSpiDev;
+use crate::{
+    dev::{
+        bus::MockBus,
+        capture::Capture,
+        cell::Shared,
+        clock::{Clock, VirtualClock},
+        event::{EventSubscriber, SharedSubscriber, SpiEvent, SpiOperation},
+        sink::{default_sink, LogSink, SharedSink},
+        spi::mock::{MockSpi, SpiError},
+    },
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, Error, ErrorKind, Mode, Operation, SpiDev,
+    SpiModeControl, Transfer, WordSizeControl,
+};
+use std::{borrow::ToOwned, format, string::String, sync::Arc, vec::Vec};
 
 /// Intercepts [`Transfer<u8>`](Transfer), providing logging capabilities.
 #[derive(Debug)]
-pub struct Spi<S: Transfer<u8>> {
+pub struct Spi<S> {
     name: String,
     spi: S,
-    opts: Rc<RefCell<SpiOpts>>,
+    opts: Shared<SpiOpts>,
+    capture: Option<Capture>,
+    sink: SharedSink,
+    subscribers: Vec<SharedSubscriber>,
+    sequence: u64,
+    selected: bool,
+    clock: Clock,
 }
 
-impl<S: Transfer<u8>> Spi<S> {
-    pub fn new(name: String, spi: S, opts: Rc<RefCell<SpiOpts>>) -> Self {
-        Self { name, spi, opts }
+impl<S> Spi<S> {
+    pub fn new(name: String, spi: S, opts: Shared<SpiOpts>) -> Self {
+        Self {
+            name,
+            spi,
+            opts,
+            capture: None,
+            sink: default_sink(),
+            subscribers: Vec::new(),
+            sequence: 0,
+            selected: false,
+            clock: Clock::Real,
+        }
+    }
+
+    /// Send an [`SpiEvent`] for every operation to the subscriber, as well
+    /// as any previous subscribers.
+    pub fn subscribe<E: EventSubscriber + 'static>(&mut self, subscriber: E) {
+        self.subscribers.push(Arc::new(subscriber));
+    }
+
+    /// Take event times from the clock.
+    pub(crate) fn set_clock(&mut self, clock: Clock) {
+        self.clock = clock;
+    }
+
+    fn emit(&mut self, operation: SpiOperation, bytes: usize, error: Option<ErrorKind>) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+
+        let event = SpiEvent {
+            time: self.clock.now(),
+            name: self.name.clone(),
+            sequence: self.sequence,
+            operation,
+            bytes,
+            selected: self.selected,
+            error,
+        };
+
+        self.sequence += 1;
+        for subscriber in self.subscribers.iter() {
+            subscriber.event(&event);
+        }
+    }
+
+    /// Send events to the sink instead of stdout.
+    pub fn set_sink<L: LogSink + 'static>(&mut self, sink: L) {
+        self.sink = Arc::new(sink);
+    }
+
+    fn log(&self, message: &str) {
+        self.sink.log(&self.name, message);
+    }
+
+    /// Record transfers into the capture.
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.capture = capture;
     }
 
     /// Set whether events are printed to stdout.
@@ -23,72 +99,460 @@ impl<S: Transfer<u8>> Spi<S> {
     pub fn set_log_bytes(&self, bytes: bool) {
         self.opts.borrow_mut().bytes = bytes;
     }
+
+    /// Print the start of a transfer. Returns whether the transfer is logged,
+    /// captured or subscribed to.
+    fn log_start(&self, len: usize) -> bool {
+        let log = self.opts.borrow().log;
+        if log {
+            self.log(&format!("Start transfer ({} bytes)", len));
+        }
+
+        log || self.capture.is_some() || !self.subscribers.is_empty()
+    }
+
+    /// Capture a successful change of clock speed.
+    fn record_clock_speed(&self, speed: u32, ok: bool) {
+        if let Some(capture) = self.capture.as_ref().filter(|_| ok) {
+            capture.record_clock_speed(speed);
+        }
+    }
+
+    /// Print, capture and emit the outcome of a transfer: the Rx bytes, or
+    /// the kind of error it failed with.
+    fn log_end(&mut self, tx: &[u8], rx: Result<&[u8], ErrorKind>) {
+        let (rx, error) = (rx.ok(), rx.err());
+        if let Some(capture) = &self.capture {
+            capture.record_transfer(tx, rx);
+        }
+
+        let bytes = rx.map_or(tx.len(), |rx| rx.len().max(tx.len()));
+        self.emit(SpiOperation::Transfer, bytes, error);
+
+        if !self.opts.borrow().log {
+            return;
+        }
+
+        match rx {
+            Some(rx) => {
+                if self.opts.borrow().bytes {
+                    self.log_summary(tx, rx)
+                }
+
+                self.log("Transfer complete");
+            }
+            None => self.log("Error (transfer failed)"),
+        };
+    }
+}
+
+impl<S: Transfer<u8>> Spi<S> {
+    /// Transfer through the wrapped bus or transport, recording the kind of
+    /// error it fails with.
+    fn logged_transfer<'w>(
+        &mut self,
+        words: &'w mut [u8],
+        kind: fn(&S::Error) -> ErrorKind,
+    ) -> Result<&'w [u8], S::Error> {
+        if !self.log_start(words.len()) {
+            return self.spi.transfer(words);
+        }
+
+        let tx = words.to_vec();
+        let result = self.spi.transfer(words);
+        let rx = result.as_ref().map(|rx| &**rx);
+        self.log_end(&tx, rx.map_err(kind));
+        result
+    }
 }
 
-impl<S: Transfer<u8>> Transfer<u8> for Spi<S> {
+impl<S: SpiDev> Transfer<u8> for Spi<S> {
     type Error = S::Error;
 
     fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
-        let opts = *self.opts.borrow();
+        self.logged_transfer(words, Error::kind)
+    }
+}
 
-        if opts.log {
-            println!("{} -> Start transfer ({} bytes)", self.name, words.len());
-            let tx = words.to_vec();
-            let result = self.spi.transfer(words);
+/// A plain [`Transfer<u8>`](Transfer) bus, intercepted with
+/// [`InterceptBuilder::init_bus`].
+#[derive(Debug)]
+pub struct HalBus<T>(pub T);
 
-            match result {
-                Ok(rx) => {
-                    if opts.bytes {
-                        print_summary(&tx, rx)
-                    }
+impl<T: Transfer<u8>> Transfer<u8> for HalBus<T> {
+    type Error = T::Error;
 
-                    println!("{} -> Transfer complete", self.name);
-                }
-                Err(_) => println!("{} -> Error (transfer failed)", self.name),
-            };
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.0.transfer(words)
+    }
+}
 
-            result
-        } else {
-            self.spi.transfer(words)
-        }
+/// The bus error is not an [`Error`], so a failed transfer is recorded as
+/// [`ErrorKind::Transfer`].
+fn bus_error<E>(_: &E) -> ErrorKind {
+    ErrorKind::Transfer
+}
+
+impl<T: Transfer<u8>> Transfer<u8> for Spi<HalBus<T>> {
+    type Error = T::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.logged_transfer(words, bus_error)
+    }
+}
+
+impl Transfer<u8> for Spi<MockSpi> {
+    type Error = SpiError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.logged_transfer(words, bus_error)
+    }
+}
+
+impl Transfer<u8> for Spi<MockBus> {
+    type Error = SpiError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.logged_transfer(words, bus_error)
     }
 }
 
 impl<S: SpiDev> SpiDev for Spi<S> {
+    type Source = S::Source;
+
     fn is_chip_select(&self) -> bool {
         self.spi.is_chip_select()
     }
 
-    fn select(&mut self) -> crate::transport::Result {
-        self.spi.select()
+    fn select(&mut self) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.select();
+        self.selected |= result.is_ok();
+        self.emit(SpiOperation::Select, 0, error_kind(&result));
+        result
     }
 
-    fn deselect(&mut self) -> crate::transport::Result {
-        self.spi.deselect()
+    fn deselect(&mut self) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.deselect();
+        self.selected &= result.is_err();
+        self.emit(SpiOperation::Deselect, 0, error_kind(&result));
+        result
     }
 
-    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> crate::transport::Result<&'w [u8]> {
-        self.spi.raw_transfer(words)
+    fn raw_transfer<'w>(
+        &mut self,
+        words: &'w mut [u8],
+    ) -> crate::transport::Result<&'w [u8], S::Source> {
+        let len = words.len();
+        let result = self.spi.raw_transfer(words);
+        self.emit(SpiOperation::RawTransfer, len, error_kind(&result));
+        result
     }
 
     fn raw_transfer_or_deselect<'w>(
         &mut self,
         words: &'w mut [u8],
-    ) -> crate::transport::Result<&'w [u8]> {
-        self.spi.raw_transfer_or_deselect(words)
+    ) -> crate::transport::Result<&'w [u8], S::Source> {
+        let len = words.len();
+        let result = self.spi.raw_transfer_or_deselect(words);
+        self.selected &= result.is_ok();
+        self.emit(
+            SpiOperation::RawTransferOrDeselect,
+            len,
+            error_kind(&result),
+        );
+        result
     }
 
     fn is_clock_speed(&self) -> bool {
         self.spi.is_clock_speed()
     }
 
-    fn set_clock_speed(&mut self, speed: u32) -> crate::transport::Result {
-        self.spi.set_clock_speed(speed)
+    fn set_clock_speed(&mut self, speed: u32) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.set_clock_speed(speed);
+        self.record_clock_speed(speed, result.is_ok());
+        self.emit(SpiOperation::SetClockSpeed(speed), 0, error_kind(&result));
+        result
+    }
+
+    fn is_spi_mode(&self) -> bool {
+        self.spi.is_spi_mode()
+    }
+
+    fn set_spi_mode(&mut self, mode: Mode) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.set_spi_mode(mode);
+        self.emit(SpiOperation::SetSpiMode(mode), 0, error_kind(&result));
+        result
+    }
+
+    fn is_bit_order(&self) -> bool {
+        self.spi.is_bit_order()
+    }
+
+    fn is_word_size(&self) -> bool {
+        self.spi.is_word_size()
+    }
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.spi.max_transfer_len()
+    }
+
+    fn set_bit_order(&mut self, order: BitOrder) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.set_bit_order(order);
+        self.emit(SpiOperation::SetBitOrder(order), 0, error_kind(&result));
+        result
+    }
+
+    fn set_word_size(&mut self, bits: u8) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.set_word_size(bits);
+        self.emit(SpiOperation::SetWordSize(bits), 0, error_kind(&result));
+        result
+    }
+
+    fn delay_us(&mut self, us: u32) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.delay_us(us);
+        self.emit(SpiOperation::DelayUs(us), 0, error_kind(&result));
+        result
+    }
+
+    /// Emits a single event for the whole transaction, which ends with the
+    /// chip deselected.
+    fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_>],
+    ) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.transaction(operations);
+        self.selected = false;
+        self.emit(
+            SpiOperation::Transaction(operations.len()),
+            operation_bytes(operations),
+            error_kind(&result),
+        );
+        result
     }
 }
 
 impl<S: ChipSelect> ChipSelect for Spi<S> {}
 impl<S: ClockSpeed> ClockSpeed for Spi<S> {}
+impl<S: SpiModeControl> SpiModeControl for Spi<S> {}
+impl<S: BitOrderControl> BitOrderControl for Spi<S> {}
+impl<S: WordSizeControl> WordSizeControl for Spi<S> {}
+
+#[cfg(feature = "eh1")]
+impl<S: _eh1::spi::ErrorType> _eh1::spi::ErrorType for Spi<S> {
+    type Error = <S as _eh1::spi::ErrorType>::Error;
+}
+
+#[cfg(feature = "eh1")]
+impl<S: _eh1::spi::SpiBus<u8>> _eh1::spi::SpiBus<u8> for Spi<S> {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let result = self.spi.read(words);
+        if observe {
+            self.log_end(&std::vec![0x00; words.len()], bus_outcome(&result, words));
+        }
+
+        result
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let result = self.spi.write(words);
+        if observe {
+            self.log_end(words, bus_outcome(&result, &[]));
+        }
+
+        result
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(read.len().max(write.len()));
+        let result = _eh1::spi::SpiBus::transfer(&mut self.spi, read, write);
+        if observe {
+            self.log_end(write, bus_outcome(&result, read));
+        }
+
+        result
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let tx = words.to_vec();
+        let result = self.spi.transfer_in_place(words);
+        if observe {
+            self.log_end(&tx, bus_outcome(&result, words));
+        }
+
+        result
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.spi.flush()
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl<S: _eh1::spi::SpiDevice<u8>> _eh1::spi::SpiDevice<u8> for Spi<S> {
+    fn transaction(
+        &mut self,
+        operations: &mut [_eh1::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        let log = self.opts.borrow().log;
+        if log {
+            self.log(&format!(
+                "Start transaction ({} operations)",
+                operations.len()
+            ));
+        }
+
+        let result = self.spi.transaction(operations);
+        if log {
+            match result {
+                Ok(_) => self.log("Transaction complete"),
+                Err(_) => self.log("Error (transaction failed)"),
+            };
+        }
+
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: _eh1_async::spi::SpiBus<u8>> _eh1_async::spi::SpiBus<u8> for Spi<S> {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let result = self.spi.read(words).await;
+        if observe {
+            self.log_end(&std::vec![0x00; words.len()], bus_outcome(&result, words));
+        }
+
+        result
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let result = self.spi.write(words).await;
+        if observe {
+            self.log_end(words, bus_outcome(&result, &[]));
+        }
+
+        result
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(read.len().max(write.len()));
+        let result = _eh1_async::spi::SpiBus::transfer(&mut self.spi, read, write).await;
+        if observe {
+            self.log_end(write, bus_outcome(&result, read));
+        }
+
+        result
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let observe = self.log_start(words.len());
+        let tx = words.to_vec();
+        let result = self.spi.transfer_in_place(words).await;
+        if observe {
+            self.log_end(&tx, bus_outcome(&result, words));
+        }
+
+        result
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.spi.flush().await
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: AsyncSpiDev> AsyncSpiDev for Spi<S> {
+    type Source = S::Source;
+
+    async fn transfer(&mut self, words: &mut [u8]) -> crate::transport::Result<(), S::Source> {
+        if !self.log_start(words.len()) {
+            return self.spi.transfer(words).await;
+        }
+
+        let tx = words.to_vec();
+        let result = self.spi.transfer(words).await;
+        let rx = result.as_ref().map(|_| &*words);
+        self.log_end(&tx, rx.map_err(Error::kind));
+        result
+    }
+
+    fn is_chip_select(&self) -> bool {
+        self.spi.is_chip_select()
+    }
+
+    fn is_clock_speed(&self) -> bool {
+        self.spi.is_clock_speed()
+    }
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.spi.max_transfer_len()
+    }
+
+    async fn select(&mut self) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.select().await;
+        self.selected |= result.is_ok();
+        self.emit(SpiOperation::Select, 0, error_kind(&result));
+        result
+    }
+
+    async fn deselect(&mut self) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.deselect().await;
+        self.selected &= result.is_err();
+        self.emit(SpiOperation::Deselect, 0, error_kind(&result));
+        result
+    }
+
+    async fn raw_transfer(&mut self, words: &mut [u8]) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.raw_transfer(words).await;
+        self.emit(SpiOperation::RawTransfer, words.len(), error_kind(&result));
+        result
+    }
+
+    async fn raw_transfer_or_deselect(
+        &mut self,
+        words: &mut [u8],
+    ) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.raw_transfer_or_deselect(words).await;
+        self.selected &= result.is_ok();
+        self.emit(
+            SpiOperation::RawTransferOrDeselect,
+            words.len(),
+            error_kind(&result),
+        );
+        result
+    }
+
+    async fn set_clock_speed(&mut self, speed: u32) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.set_clock_speed(speed).await;
+        self.record_clock_speed(speed, result.is_ok());
+        self.emit(SpiOperation::SetClockSpeed(speed), 0, error_kind(&result));
+        result
+    }
+
+    async fn delay_us(&mut self, us: u32) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.delay_us(us).await;
+        self.emit(SpiOperation::DelayUs(us), 0, error_kind(&result));
+        result
+    }
+
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_>],
+    ) -> crate::transport::Result<(), S::Source> {
+        let result = self.spi.transaction(operations).await;
+        self.selected = false;
+        self.emit(
+            SpiOperation::Transaction(operations.len()),
+            operation_bytes(operations),
+            error_kind(&result),
+        );
+        result
+    }
+}
 
 /// Options for constructing an SPI intercept.
 #[derive(Debug, Clone, Copy, Default)]
@@ -106,7 +570,11 @@ impl SpiOpts {
     }
 }
 
-builder!(InterceptBuilder<SpiOpts> + Debug, Clone {});
+builder!(InterceptBuilder<SpiOpts> + Debug, Clone {
+    capture: Option<Capture> = None,
+    subscribers: Vec<SharedSubscriber> = Vec::new(),
+    clock: Clock = Clock::Real,
+});
 
 impl InterceptBuilder {
     pub fn with_byte_log(mut self) -> Self {
@@ -115,11 +583,69 @@ impl InterceptBuilder {
         self
     }
 
-    pub fn init<S: Transfer<u8>>(self, spi: S) -> Spi<S> {
-        Spi::new(self.name, spi, Rc::new(RefCell::new(self.opts)))
+    /// Record transfers into the capture.
+    pub fn with_capture(mut self, capture: &Capture) -> Self {
+        self.capture = Some(capture.clone());
+        self
+    }
+
+    /// Send an [`SpiEvent`] for every operation to the subscriber. May be
+    /// called several times.
+    pub fn with_subscriber<E: EventSubscriber + 'static>(mut self, subscriber: E) -> Self {
+        self.subscribers.push(Arc::new(subscriber));
+        self
+    }
+
+    /// Take event times from the virtual clock.
+    pub fn with_clock(mut self, clock: &VirtualClock) -> Self {
+        self.clock = Clock::Virtual(clock.clone());
+        self
+    }
+
+    /// Intercept a transport, or a mock or embedded-hal 1.0 bus.
+    pub fn init<S>(self, spi: S) -> Spi<S> {
+        let mut spi = Spi::new(self.name, spi, Shared::new(self.opts));
+        spi.set_capture(self.capture);
+        spi.subscribers = self.subscribers;
+        spi.set_clock(self.clock);
+
+        if let Some(sink) = self.sink {
+            spi.set_sink(sink);
+        }
+
+        spi
+    }
+
+    /// Intercept a plain [`Transfer<u8>`](Transfer) bus, such as an SPI
+    /// peripheral, to build a transport on.
+    pub fn init_bus<T: Transfer<u8>>(self, spi: T) -> Spi<HalBus<T>> {
+        self.init(HalBus(spi))
     }
 }
 
+/// The kind of error the operation failed with, if it failed.
+fn error_kind<T, E>(result: &crate::transport::Result<T, E>) -> Option<ErrorKind> {
+    result.as_ref().err().map(Error::kind)
+}
+
+/// The Rx bytes of a bus operation, or the kind of its error.
+#[cfg(feature = "eh1")]
+fn bus_outcome<'a, E>(result: &Result<(), E>, rx: &'a [u8]) -> Result<&'a [u8], ErrorKind> {
+    result.as_ref().map(|_| rx).map_err(bus_error)
+}
+
+/// The number of bytes exchanged by the operations.
+fn operation_bytes(operations: &[Operation<'_>]) -> usize {
+    operations
+        .iter()
+        .map(|operation| match operation {
+            Operation::Write(words) => words.len(),
+            Operation::Read(words) | Operation::Transfer(words) => words.len(),
+            Operation::DelayUs(_) => 0,
+        })
+        .sum()
+}
+
 fn printable_bytes(bytes: &[u8]) -> String {
     bytes
         .iter()
@@ -128,10 +654,15 @@ fn printable_bytes(bytes: &[u8]) -> String {
         .join(" ")
 }
 
-fn print_summary(tx: &[u8], rx: &[u8]) {
-    for (chunk, (tx, rx)) in tx.chunks(16).zip(rx.chunks(16)).enumerate() {
-        let range = format!("{}-{}", chunk * 16, (chunk * 16) + 16.min(tx.len()));
-        println!("{: >12} --> {} -->", range, printable_bytes(tx));
-        println!("{: >12} <-- {} <--", "", printable_bytes(rx))
+impl<S> Spi<S> {
+    fn log_summary(&self, tx: &[u8], rx: &[u8]) {
+        for (chunk, tx) in tx.chunks(16).enumerate() {
+            let range = format!("{}-{}", chunk * 16, (chunk * 16) + 16.min(tx.len()));
+            self.log(&format!("{: >12} --> {} -->", range, printable_bytes(tx)));
+
+            if let Some(rx) = rx.chunks(16).nth(chunk) {
+                self.log(&format!("{: >12} <-- {} <--", "", printable_bytes(rx)))
+            }
+        }
     }
 }
@@ -1,18 +1,37 @@
-use super::intercept::{Spi, SpiOpts};
+use super::{
+    super::{
+        capture::Capture,
+        cell::Shared,
+        clock::{Clock, VirtualClock},
+        fault::{FaultCounts, FaultInjector, FaultPolicy},
+        output::mock::PinControl,
+    },
+    expect::{Expectation, ExpectationError, Frame},
+    intercept::{Spi, SpiOpts},
+    model::{BoxedModel, SpiDeviceModel},
+};
+use crate::Polarity;
 use embedded_hal::blocking::spi::Transfer;
 use std::{
-    borrow::ToOwned, boxed::Box, cell::RefCell, fmt, rc::Rc, string::String, thread,
-    time::Duration, vec::Vec,
+    borrow::ToOwned,
+    boxed::Box,
+    collections::VecDeque,
+    fmt, io,
+    path::Path,
+    string::{String, ToString},
+    thread,
+    time::{Duration, Instant},
+    vec::Vec,
 };
 
 /// Transfer interface for Mock SPI.
 #[derive(Debug)]
 pub struct MockSpi {
-    dev: Rc<RefCell<MockSpiDevice>>,
+    dev: Shared<MockSpiDevice>,
 }
 
 impl MockSpi {
-    fn new(dev: Rc<RefCell<MockSpiDevice>>) -> Self {
+    fn new(dev: Shared<MockSpiDevice>) -> Self {
         Self { dev }
     }
 }
@@ -24,14 +43,121 @@ impl Transfer<u8> for MockSpi {
     }
 }
 
+#[cfg(feature = "eh1")]
+impl _eh1::spi::ErrorType for MockSpi {
+    type Error = SpiError;
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::SpiBus<u8> for MockSpi {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.fill(0x00);
+        Transfer::transfer(self, words).and(Ok(()))
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        Transfer::transfer(self, &mut words.to_vec()).and(Ok(()))
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let mut words = write.to_vec();
+        words.resize(read.len().max(write.len()), 0x00);
+        Transfer::transfer(self, &mut words)?;
+
+        let len = read.len();
+        read.copy_from_slice(&words[..len]);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        Transfer::transfer(self, words).and(Ok(()))
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl _eh1_async::spi::SpiBus<u8> for MockSpi {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        _eh1::spi::SpiBus::read(self, words)
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        _eh1::spi::SpiBus::write(self, words)
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        _eh1::spi::SpiBus::transfer(self, read, write)
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        _eh1::spi::SpiBus::transfer_in_place(self, words)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
 /// An enum of mock SPI errors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SpiError {
     Transfer,
+    /// A transfer on a [`MockBus`](crate::dev::bus::MockBus) did not select
+    /// exactly one chip.
+    Violation,
+    /// A transfer was longer than the maximum set with
+    /// [`SpiControl::set_max_transfer_len`].
+    TooLong,
+    /// A transfer departed from the expectations. See
+    /// [`SpiControl::verify`].
+    Unexpected,
+    /// A deferred mock error or an injected fault failed a transfer after
+    /// `completed` bytes were exchanged.
+    Partial {
+        completed: usize,
+    },
+}
+
+impl SpiError {
+    /// The number of bytes exchanged before the failure, if known. Pass it
+    /// to `with_progress` when building a [`Transport::hal`](crate::Transport::hal)
+    /// with a chip select pin, so that its errors count these bytes.
+    pub fn completed(&self) -> Option<usize> {
+        match self {
+            SpiError::Partial { completed } => Some(*completed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SpiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpiError::Transfer => write!(f, "Mock SPI transfer error"),
+            SpiError::Violation => write!(f, "Mock SPI bus violation"),
+            SpiError::TooLong => write!(f, "Mock SPI transfer too long"),
+            SpiError::Unexpected => write!(f, "Mock SPI transfer not expected"),
+            SpiError::Partial { completed } => {
+                write!(f, "Mock SPI transfer error after {} bytes", completed)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpiError {}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::Error for SpiError {
+    fn kind(&self) -> _eh1::spi::ErrorKind {
+        _eh1::spi::ErrorKind::Other
+    }
 }
 
 /// Indicates that the function can be used to generate bytes for mock SPI.
-pub trait ByteGenerator: Fn(&[u8]) -> Vec<u8> {}
+pub trait ByteGenerator: Fn(&[u8]) -> Vec<u8> + Send + Sync {}
 
 /// A boxed byte generator for mock SPI
 pub type BoxedGenerator = Box<dyn ByteGenerator>;
@@ -39,7 +165,7 @@ pub type BoxedGenerator = Box<dyn ByteGenerator>;
 /// A byte generator for mock SPI
 pub type Generator = fn(&[u8]) -> Vec<u8>;
 
-impl ByteGenerator for fn(&[u8]) -> Vec<u8> {}
+impl<F: Fn(&[u8]) -> Vec<u8> + Send + Sync> ByteGenerator for F {}
 
 impl fmt::Debug for dyn ByteGenerator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -50,21 +176,95 @@ impl fmt::Debug for dyn ByteGenerator {
 /// Holds the underlying state shared by a [MockSpi] and an [SpiControl].
 #[derive(Debug)]
 struct MockSpiDevice {
-    opts: Rc<RefCell<SpiOpts>>,
-    generator: Option<BoxedGenerator>,
+    opts: Shared<SpiOpts>,
+    model: Option<BoxedModel>,
+    cs: bool,
     byte_delay: Option<Duration>,
     error: Option<SpiError>,
     error_after_bytes: usize,
+    transfers: Vec<(Instant, Instant)>,
+    traffic: Vec<(Vec<u8>, Vec<u8>)>,
+    expectations: Option<VecDeque<Expectation>>,
+    consumed: usize,
+    frame: Option<Frame>,
+    mismatches: Vec<ExpectationError>,
+    clock: Clock,
+    faults: Option<FaultInjector>,
+    max_transfer_len: Option<usize>,
 }
 
 impl MockSpiDevice {
-    pub fn new(opts: Rc<RefCell<SpiOpts>>) -> Self {
+    pub fn new(opts: Shared<SpiOpts>) -> Self {
         Self {
             opts,
-            generator: None,
+            model: None,
+            cs: false,
             byte_delay: None,
             error: None,
             error_after_bytes: 0,
+            transfers: Vec::new(),
+            traffic: Vec::new(),
+            expectations: None,
+            consumed: 0,
+            frame: None,
+            mismatches: Vec::new(),
+            clock: Clock::Real,
+            faults: None,
+            max_transfer_len: None,
+        }
+    }
+
+    /// Match the transfer against the expectation of the current frame,
+    /// consuming the next one if the frame has just started. Without a chip
+    /// select pin attached, the frame ends with the transfer.
+    fn expect(&mut self, tx: &[u8]) -> Result<Vec<u8>, SpiError> {
+        let (expectations, consumed) = (&mut self.expectations, &mut self.consumed);
+        let frame = self.frame.get_or_insert_with(|| {
+            let index = *consumed;
+            let expectation = expectations.get_or_insert_with(VecDeque::new).pop_front();
+            *consumed += usize::from(expectation.is_some());
+            Frame::new(index, expectation)
+        });
+
+        let rx = frame.exchange(tx, &mut self.mismatches);
+        if !self.cs {
+            self.end_frame();
+        }
+
+        rx
+    }
+
+    fn end_frame(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            frame.end(&mut self.mismatches);
+        }
+    }
+
+    fn verify(&self) -> Result<(), Vec<ExpectationError>> {
+        let mut errors = self.mismatches.clone();
+
+        if let Some(remaining) = self.expectations.as_ref().filter(|e| !e.is_empty()) {
+            errors.push(ExpectationError::Unconsumed {
+                remaining: remaining.iter().cloned().collect(),
+            });
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+}
+
+impl Drop for MockSpiDevice {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            return;
+        }
+
+        if let Err(errors) = self.verify() {
+            let report: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            panic!("{}", report.join("\n"));
         }
     }
 }
@@ -72,39 +272,90 @@ impl MockSpiDevice {
 impl Transfer<u8> for MockSpiDevice {
     type Error = SpiError;
 
+    /// Exchange the bytes before any mock error or fault with the
+    /// expectations or the model. Without a chip select pin attached, the
+    /// transfer is a frame for the model.
+    ///
+    /// A deferred mock error or an injected fault fails the transfer after
+    /// the bytes before it were exchanged, with [`SpiError::Partial`] if
+    /// there were any. A transfer longer than the maximum exchanges nothing.
     fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
-        let rx = match &mut self.generator {
-            Some(generator) => generator(words),
-            None => Vec::new(),
-        };
-
-        for (index, word) in words.iter_mut().enumerate() {
-            if self.error.is_some() && self.error_after_bytes == index {
-                self.error_after_bytes = 0;
-                return Err(self.error.take().unwrap());
+        if self.max_transfer_len.is_some_and(|max| words.len() > max) {
+            return Err(SpiError::TooLong);
+        }
+
+        let start = self.clock.now();
+        let tx = words.to_vec();
+        let deferred = Some(self.error_after_bytes)
+            .filter(|index| self.error.is_some() && *index < words.len());
+        let fault = self
+            .faults
+            .as_mut()
+            .and_then(|faults| faults.fail_at(words.len()));
+        let len = deferred
+            .into_iter()
+            .chain(fault)
+            .min()
+            .unwrap_or(words.len());
+
+        match (&self.expectations, &mut self.model) {
+            (Some(_), _) => {
+                let rx = self.expect(words)?;
+                for (index, word) in words[..len].iter_mut().enumerate() {
+                    *word = *rx.get(index).unwrap_or(&0x00);
+                }
+            }
+            (None, Some(model)) if self.cs => model.exchange(&mut words[..len])?,
+            (None, Some(model)) => {
+                model.on_select()?;
+                model.exchange(&mut words[..len])?;
+                model.on_deselect()?;
             }
+            (None, None) => words[..len].fill(0x00),
+        }
 
-            *word = *rx.get(index).unwrap_or(&0x00);
+        if let Some(faults) = &mut self.faults {
+            faults.corrupt(&mut words[..len]);
+        }
 
-            if self.byte_delay.is_some() {
-                thread::sleep(self.byte_delay.unwrap());
-            }
+        if let Some(delay) = self.byte_delay {
+            (0..len).for_each(|_| self.clock.sleep(delay));
+        }
+
+        if deferred == Some(len) {
+            self.error_after_bytes = 0;
+            return Err(partial(self.error.take().unwrap(), len));
+        }
+
+        if fault.is_some() {
+            self.error_after_bytes = self.error_after_bytes.saturating_sub(len);
+            return Err(partial(SpiError::Transfer, len));
         }
 
-        self.error_after_bytes = self.error_after_bytes.saturating_sub(words.len());
+        self.error_after_bytes = self.error_after_bytes.saturating_sub(len);
+        self.transfers.push((start, self.clock.now()));
+        self.traffic.push((tx, words.to_vec()));
 
         Ok(words)
     }
 }
 
+/// The error of a transfer which failed after `len` bytes.
+fn partial(error: SpiError, len: usize) -> SpiError {
+    match len {
+        0 => error,
+        completed => SpiError::Partial { completed },
+    }
+}
+
 /// Developer controls for mock SPI.
 #[derive(Debug)]
 pub struct SpiControl {
-    spi: Rc<RefCell<MockSpiDevice>>,
+    spi: Shared<MockSpiDevice>,
 }
 
 impl SpiControl {
-    fn new(spi: Rc<RefCell<MockSpiDevice>>) -> Self {
+    fn new(spi: Shared<MockSpiDevice>) -> Self {
         Self { spi }
     }
 
@@ -122,22 +373,65 @@ impl SpiControl {
 
     /// Use this function to provide Rx bytes.
     pub fn set_generator(&self, generator: Generator) -> &Self {
-        self.spi.borrow_mut().generator = Some(Box::new(generator));
-        self
+        self.set_model(generator)
     }
 
     /// Use this boxed function to provide Rx bytes.
     pub fn set_boxed_generator(&self, generator: BoxedGenerator) -> &Self {
-        self.spi.borrow_mut().generator = Some(generator);
-        self
+        self.set_model(generator)
     }
 
     /// Clear the Rx byte generator function (if set).
     pub fn clear_generator(&self) -> &Self {
-        self.spi.borrow_mut().generator = None;
+        self.clear_model()
+    }
+
+    /// Use this model to provide Rx bytes, replacing any generator.
+    pub fn set_model<M: SpiDeviceModel + 'static>(&self, model: M) -> &Self {
+        self.spi.borrow_mut().model = Some(Box::new(model));
+        self
+    }
+
+    /// Clear the model or generator (if set).
+    pub fn clear_model(&self) -> &Self {
+        self.spi.borrow_mut().model = None;
         self
     }
 
+    /// Notify the model when the mock chip select pin is selected or
+    /// deselected, instead of around each transfer. A model error fails the
+    /// pin change.
+    pub fn attach_cs(&self, cs: &PinControl, polarity: Polarity) -> &Self {
+        let active = polarity == Polarity::IdleLow;
+        let spi = self.spi.clone();
+        self.spi.borrow_mut().cs = true;
+
+        cs.set_listener(Box::new(move |value| {
+            let mut spi = spi.borrow_mut();
+            if value != active {
+                spi.end_frame();
+            }
+
+            match (&mut spi.model, value == active) {
+                (Some(model), true) => model.on_select(),
+                (Some(model), false) => model.on_deselect(),
+                (None, _) => Ok(()),
+            }
+            .or(Err(()))
+        }));
+
+        self
+    }
+
+    /// Tell the model the clock speed has changed, as a transport with
+    /// clock speed control would.
+    pub fn set_clock_speed(&self, speed: u32) -> Result<(), SpiError> {
+        match &mut self.spi.borrow_mut().model {
+            Some(model) => model.on_clock_speed(speed),
+            None => Ok(()),
+        }
+    }
+
     /// Set the per-byte time delay for transfers.
     pub fn set_byte_delay(&self, duration: Duration) -> &Self {
         self.spi.borrow_mut().byte_delay = Some(duration);
@@ -151,7 +445,8 @@ impl SpiControl {
     }
 
     /// Set a mock error. The next time this error could occur - it does.
-    /// The error may deferred with `set_error_defer_bytes`.
+    /// The error may deferred with `set_error_defer_bytes`, failing with
+    /// [`SpiError::Partial`] if part of a transfer was exchanged.
     pub fn set_error(&self, error: SpiError) -> &Self {
         self.spi.borrow_mut().error = Some(error);
         self
@@ -169,11 +464,95 @@ impl SpiControl {
         self.spi.borrow_mut().error = None;
         self
     }
+
+    /// Fail transfers longer than `max` bytes with [`SpiError::TooLong`],
+    /// as a peripheral with a limited buffer would. `None` removes the
+    /// limit.
+    pub fn set_max_transfer_len(&self, max: Option<usize>) -> &Self {
+        self.spi.borrow_mut().max_transfer_len = max;
+        self
+    }
+
+    /// Inject random faults into transfers, replacing any previous policy
+    /// and its counts.
+    pub fn set_faults(&self, policy: FaultPolicy) -> &Self {
+        self.spi.borrow_mut().faults = Some(FaultInjector::new(policy));
+        self
+    }
+
+    /// Stop injecting faults, and forget their counts.
+    pub fn clear_faults(&self) -> &Self {
+        self.spi.borrow_mut().faults = None;
+        self
+    }
+
+    /// Get the number of faults injected since the policy was set.
+    pub fn get_fault_counts(&self) -> FaultCounts {
+        self.spi
+            .borrow()
+            .faults
+            .as_ref()
+            .map(FaultInjector::counts)
+            .unwrap_or_default()
+    }
+
+    /// Get the start and end time of each successful transfer.
+    pub fn get_transfers(&self) -> Vec<(Instant, Instant)> {
+        self.spi.borrow().transfers.clone()
+    }
+
+    /// Get the Tx and Rx bytes of each successful transfer.
+    pub fn get_traffic(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.spi.borrow().traffic.clone()
+    }
+
+    /// Forget the recorded transfer times and bytes.
+    pub fn clear_transfers(&self) -> &Self {
+        let mut spi = self.spi.borrow_mut();
+        spi.transfers.clear();
+        spi.traffic.clear();
+        self
+    }
+
+    /// Expect a frame after any previously expected frames. Once an
+    /// expectation is set, transfers are answered by expectations instead
+    /// of the generator. A transfer which does not match fails with
+    /// [`SpiError::Unexpected`], and [`verify`](Self::verify) returns the
+    /// diff.
+    pub fn expect(&self, expectation: Expectation) -> &Self {
+        self.spi
+            .borrow_mut()
+            .expectations
+            .get_or_insert_with(VecDeque::new)
+            .push_back(expectation);
+        self
+    }
+
+    /// Check that every frame matched its expectation and every expectation
+    /// has been consumed, returning each departure in order. This is also
+    /// checked (with a panic) when the mock and its controller are dropped.
+    pub fn verify(&self) -> Result<(), Vec<ExpectationError>> {
+        self.spi.borrow().verify()
+    }
+
+    /// Forget any unconsumed expectations and return to using the generator.
+    pub fn clear_expectations(&self) -> &Self {
+        let mut spi = self.spi.borrow_mut();
+        spi.expectations = None;
+        spi.consumed = 0;
+        spi.frame = None;
+        spi.mismatches.clear();
+        self
+    }
 }
 
 builder!(MockBuilder<SpiOpts> + Debug {
     byte_delay: Option<Duration> = None,
-    generator: Option<BoxedGenerator> = None,
+    model: Option<BoxedModel> = None,
+    expectations: Option<Vec<Expectation>> = None,
+    clock: Clock = Clock::Real,
+    faults: Option<FaultPolicy> = None,
+    max_transfer_len: Option<usize> = None,
 });
 
 impl MockBuilder {
@@ -185,14 +564,49 @@ impl MockBuilder {
     }
 
     /// Use a function to provide Rx bytes.
-    pub fn with_generator(mut self, generator: Generator) -> Self {
-        self.generator = Some(Box::new(generator));
-        self
+    pub fn with_generator(self, generator: Generator) -> Self {
+        self.with_model(generator)
     }
 
     /// Use a boxed function to provide Rx bytes.
-    pub fn with_boxed_generator(mut self, generator: BoxedGenerator) -> Self {
-        self.generator = Some(generator);
+    pub fn with_boxed_generator(self, generator: BoxedGenerator) -> Self {
+        self.with_model(generator)
+    }
+
+    /// Use a model to provide Rx bytes. See [`SpiDeviceModel`].
+    pub fn with_model<M: SpiDeviceModel + 'static>(mut self, model: M) -> Self {
+        self.model = Some(Box::new(model));
+        self
+    }
+
+    /// Replay the Rx bytes recorded in the capture. See
+    /// [`Capture::generator`].
+    pub fn with_capture(self, capture: &Capture) -> Self {
+        self.with_boxed_generator(capture.generator())
+    }
+
+    /// Replay the Rx bytes recorded in a capture file.
+    pub fn from_capture<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        Ok(self.with_capture(&Capture::load(path)?))
+    }
+
+    /// Advance the virtual clock for byte delays instead of sleeping, and
+    /// record times from it.
+    pub fn with_clock(mut self, clock: &VirtualClock) -> Self {
+        self.clock = Clock::Virtual(clock.clone());
+        self
+    }
+
+    /// Inject random faults into transfers. See [`FaultPolicy`].
+    pub fn with_faults(mut self, policy: FaultPolicy) -> Self {
+        self.faults = Some(policy);
+        self
+    }
+
+    /// Fail transfers longer than `max` bytes. See
+    /// [`SpiControl::set_max_transfer_len`].
+    pub fn with_max_transfer_len(mut self, max: usize) -> Self {
+        self.max_transfer_len = Some(max);
         self
     }
 
@@ -202,19 +616,43 @@ impl MockBuilder {
         self
     }
 
+    /// Expect these frames in order. See [`SpiControl::expect`].
+    pub fn with_expectations<I: IntoIterator<Item = Expectation>>(
+        mut self,
+        expectations: I,
+    ) -> Self {
+        self.expectations
+            .get_or_insert_with(Vec::new)
+            .extend(expectations);
+        self
+    }
+
     /// Create the mock device and controller.
     pub fn init(self) -> (Spi<MockSpi>, SpiControl) {
-        let opts = Rc::new(RefCell::new(self.opts));
-        let dev = Rc::new(RefCell::new(MockSpiDevice::new(opts.clone())));
+        let opts = Shared::new(self.opts);
+        let dev = Shared::new(MockSpiDevice::new(opts.clone()));
+        dev.borrow_mut().clock = self.clock.clone();
         let control = SpiControl::new(dev.clone());
-        let pin = Spi::new(self.name, MockSpi::new(dev), opts);
+        let mut pin = Spi::new(self.name, MockSpi::new(dev), opts);
+        pin.set_clock(self.clock);
 
         if let Some(delay) = self.byte_delay {
             control.set_byte_delay(delay);
         }
 
-        if let Some(generator) = self.generator {
-            control.set_boxed_generator(generator);
+        control.spi.borrow_mut().model = self.model;
+        control.set_max_transfer_len(self.max_transfer_len);
+
+        if let Some(policy) = self.faults {
+            control.set_faults(policy);
+        }
+
+        if let Some(expectations) = self.expectations {
+            control.spi.borrow_mut().expectations = Some(expectations.into());
+        }
+
+        if let Some(sink) = self.sink {
+            pin.set_sink(sink);
         }
 
         (pin, control)
@@ -1,2 +1,4 @@
+pub mod expect;
 pub mod intercept;
 pub mod mock;
+pub mod model;
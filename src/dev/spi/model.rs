@@ -0,0 +1,90 @@
+use super::mock::{ByteGenerator, SpiError};
+use std::{boxed::Box, fmt};
+
+/// A chip emulated by mock SPI, exchanging one byte for another while it is
+/// selected.
+///
+/// Every method may fail with an [`SpiError`], which fails the transfer, or
+/// the chip select pin change, which caused it. When mock SPI has no chip
+/// select pin [attached](super::mock::SpiControl::attach_cs), each transfer
+/// is a frame between [`on_select`](Self::on_select) and
+/// [`on_deselect`](Self::on_deselect).
+///
+/// Byte generators are models answering each transfer as a whole, which see
+/// a single byte at a time when used with
+/// [`exchange_byte`](Self::exchange_byte).
+///
+/// ```
+/// use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
+///
+/// /// Answers each byte with the previous one.
+/// #[derive(Default)]
+/// struct Echo(u8);
+///
+/// impl SpiDeviceModel for Echo {
+///     fn on_select(&mut self) -> Result<(), SpiError> {
+///         self.0 = 0x00;
+///         Ok(())
+///     }
+///
+///     fn exchange_byte(&mut self, tx: u8) -> Result<u8, SpiError> {
+///         Ok(core::mem::replace(&mut self.0, tx))
+///     }
+/// }
+///
+/// let (spi, _) = Mock::spi("Echo").without_log().with_model(Echo::default()).init();
+/// let mut spi = Transport::hal(spi).init();
+/// assert_eq!(spi.transfer(&mut [0x01, 0x02, 0x03]).unwrap(), &[0x00, 0x01, 0x02]);
+/// ```
+pub trait SpiDeviceModel: Send + Sync {
+    /// Called when the chip is selected.
+    fn on_select(&mut self) -> Result<(), SpiError> {
+        Ok(())
+    }
+
+    /// Called when the chip is deselected.
+    fn on_deselect(&mut self) -> Result<(), SpiError> {
+        Ok(())
+    }
+
+    /// Answer a Tx byte with an Rx byte, clocked at the same time.
+    fn exchange_byte(&mut self, tx: u8) -> Result<u8, SpiError>;
+
+    /// Replace Tx bytes with Rx bytes, one at a time by default.
+    fn exchange(&mut self, words: &mut [u8]) -> Result<(), SpiError> {
+        words.iter_mut().try_for_each(|word| {
+            *word = self.exchange_byte(*word)?;
+            Ok(())
+        })
+    }
+
+    /// Called when the clock speed is changed.
+    fn on_clock_speed(&mut self, _speed: u32) -> Result<(), SpiError> {
+        Ok(())
+    }
+}
+
+impl<G: ByteGenerator> SpiDeviceModel for G {
+    fn exchange_byte(&mut self, tx: u8) -> Result<u8, SpiError> {
+        Ok(self(&[tx]).first().copied().unwrap_or(0x00))
+    }
+
+    /// Answer with the generated bytes, padded with zeros.
+    fn exchange(&mut self, words: &mut [u8]) -> Result<(), SpiError> {
+        let rx = self(words);
+        for (index, word) in words.iter_mut().enumerate() {
+            *word = *rx.get(index).unwrap_or(&0x00);
+        }
+
+        Ok(())
+    }
+}
+
+/// A boxed model for mock SPI
+pub type BoxedModel = Box<dyn SpiDeviceModel>;
+
+impl fmt::Debug for dyn SpiDeviceModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Device Model")
+    }
+}
@@ -0,0 +1,144 @@
+use super::{clock::VirtualClock, output::mock::PinControl, spi::mock::SpiControl};
+use crate::{Polarity, Timing};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use std::{fmt, thread, time::Duration};
+
+/// A [`DelayUs`] and [`DelayMs`] which sleeps the current thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockDelay;
+
+impl DelayUs<u32> for MockDelay {
+    fn delay_us(&mut self, us: u32) {
+        thread::sleep(Duration::from_micros(us.into()));
+    }
+}
+
+impl DelayMs<u32> for MockDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        thread::sleep(Duration::from_millis(ms.into()));
+    }
+}
+
+/// A [`DelayUs`] and [`DelayMs`] which advances a [`VirtualClock`] instead
+/// of sleeping.
+#[derive(Debug, Clone)]
+pub struct VirtualDelay {
+    clock: VirtualClock,
+}
+
+impl VirtualDelay {
+    pub fn new(clock: &VirtualClock) -> Self {
+        Self {
+            clock: clock.clone(),
+        }
+    }
+}
+
+impl DelayUs<u32> for VirtualDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.clock.advance(Duration::from_micros(us.into()));
+    }
+}
+
+impl DelayMs<u32> for VirtualDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.clock.advance(Duration::from_millis(ms.into()));
+    }
+}
+
+/// Serves [`Operation::DelayUs`](crate::Operation::DelayUs) on async
+/// transports.
+#[cfg(feature = "async")]
+impl _eh1_async::delay::DelayNs for VirtualDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.clock.advance(Duration::from_nanos(ns.into()));
+    }
+}
+
+/// A chip select timing requirement which was not met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingViolation {
+    /// The first transfer of the frame started too soon after select.
+    Setup { frame: usize, actual: Duration },
+    /// The chip was deselected too soon after the last transfer.
+    Hold { frame: usize, actual: Duration },
+    /// The chip was selected too soon after the previous deselect.
+    Idle { frame: usize, actual: Duration },
+}
+
+impl fmt::Display for TimingViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingViolation::Setup { frame, actual } => {
+                write!(f, "Frame {}: setup time was {:?}", frame, actual)
+            }
+            TimingViolation::Hold { frame, actual } => {
+                write!(f, "Frame {}: hold time was {:?}", frame, actual)
+            }
+            TimingViolation::Idle { frame, actual } => {
+                write!(f, "Frame {}: idle time was {:?}", frame, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimingViolation {}
+
+/// Check the recorded activity of a mock chip select pin and mock SPI
+/// against a [`Timing`]. A frame runs from each select to the following
+/// deselect; frames without transfers are only checked for idle time.
+pub fn check_timing(
+    cs: &PinControl,
+    spi: &SpiControl,
+    polarity: Polarity,
+    timing: Timing,
+) -> Result<(), TimingViolation> {
+    let active = polarity == Polarity::IdleLow;
+    let edges = cs.get_edges();
+    let transfers = spi.get_transfers();
+    let mut deselected = None;
+    let mut selected = None;
+    let mut frame = 0;
+
+    let at_least = |us: u32, actual: Duration| actual >= Duration::from_micros(us.into());
+
+    for (time, value) in edges {
+        if value == active {
+            if let Some(deselected) = deselected {
+                let actual = time.saturating_duration_since(deselected);
+                if !at_least(timing.idle_us, actual) {
+                    return Err(TimingViolation::Idle { frame, actual });
+                }
+            }
+
+            selected = Some(time);
+            continue;
+        }
+
+        if let Some(selected) = selected.take() {
+            let mut in_frame = transfers
+                .iter()
+                .filter(|(start, end)| *start >= selected && *end <= time);
+
+            if let Some((first, end)) = in_frame.next() {
+                let last = in_frame.next_back().map_or(end, |(_, end)| end);
+
+                let actual = first.saturating_duration_since(selected);
+                if !at_least(timing.setup_us, actual) {
+                    return Err(TimingViolation::Setup { frame, actual });
+                }
+
+                let actual = time.saturating_duration_since(*last);
+                if !at_least(timing.hold_us, actual) {
+                    return Err(TimingViolation::Hold { frame, actual });
+                }
+            }
+
+            frame += 1;
+        }
+
+        deselected = Some(time);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,304 @@
+//! Export a [`Capture`] as a Value Change Dump, to view the recorded
+//! activity as waveforms in GTKWave, PulseView or similar.
+//!
+//! Pin edges and clock speed changes are written as recorded. SCLK, MOSI
+//! and MISO are reconstructed from each transfer at the clock speed of the
+//! time, ending when the transfer was recorded. A transfer which would then
+//! start before the events recorded ahead of it starts after them instead,
+//! and the events after it move along. MISO is unknown (`x`) during failed
+//! transfers.
+//!
+//! ```ignore
+//! let capture = Capture::new();
+//! let spi = Intercept::spi("Sensor").with_capture(&capture).init_bus(real_spi);
+//! let cs = Intercept::pin("SensorCS").with_capture(&capture).init(real_cs_pin);
+//!
+//! // ... run the driver, then:
+//! capture.save_vcd("sensor.vcd", VcdOptions::new().with_mode(MODE_3))?;
+//! ```
+
+use super::capture::{Capture, CaptureEvent};
+use crate::{BitOrder, Mode, Polarity};
+use embedded_hal::spi::{Phase, MODE_0};
+use std::{
+    borrow::ToOwned,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+/// How to reconstruct bus signals from transfers.
+#[derive(Clone, Copy)]
+pub struct VcdOptions {
+    mode: Mode,
+    bit_order: BitOrder,
+    clock_speed: u32,
+}
+
+impl Default for VcdOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VcdOptions {
+    /// SPI mode 0, most significant bit first, and 1 MHz until the capture
+    /// records a clock speed.
+    pub const fn new() -> Self {
+        Self {
+            mode: MODE_0,
+            bit_order: BitOrder::MsbFirst,
+            clock_speed: 1_000_000,
+        }
+    }
+
+    /// Use the SPI mode (clock polarity and phase).
+    pub const fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Use the order in which the bits of each byte are clocked.
+    pub const fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Use the clock speed in Hz until the capture records one.
+    pub const fn with_clock_speed(mut self, speed: u32) -> Self {
+        self.clock_speed = speed;
+        self
+    }
+}
+
+const SCLK: usize = 0;
+const MOSI: usize = 1;
+const MISO: usize = 2;
+const CLOCK_SPEED: usize = 3;
+const PINS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    Bit(Option<bool>),
+    Integer(u32),
+}
+
+/// Value changes of the signals, as (time in ns, signal, value).
+type Changes = Vec<(u64, usize, Value)>;
+
+impl Capture {
+    /// Write the events as a Value Change Dump, with times in nanoseconds.
+    ///
+    /// ```
+    /// use rpio_utils::dev::*;
+    /// use std::time::Duration;
+    ///
+    /// let us = Duration::from_micros;
+    /// let capture = Capture::from_events(vec![
+    ///     CaptureEvent::Edge { time: us(100), pin: "CS".into(), high: false },
+    ///     CaptureEvent::Transfer { time: us(102), tx: vec![0xa5; 4], rx: vec![0x00; 4] },
+    ///     CaptureEvent::Edge { time: us(103), pin: "CS".into(), high: true },
+    /// ]);
+    ///
+    /// let mut vcd = Vec::new();
+    /// capture.write_vcd(&mut vcd, VcdOptions::new()).unwrap();
+    ///
+    /// // The times at which SCLK (`!`) and CS (`%`) change
+    /// let (mut time, mut sclk, mut cs) = (0, Vec::new(), Vec::new());
+    /// let vcd = String::from_utf8(vcd).unwrap();
+    /// for line in vcd.lines().skip_while(|line| *line != "$end") {
+    ///     match line {
+    ///         _ if line.starts_with('#') => time = line[1..].parse::<u64>().unwrap(),
+    ///         "0!" | "1!" => sclk.push(time),
+    ///         "0%" | "1%" => cs.push(time),
+    ///         _ => {}
+    ///     }
+    /// }
+    ///
+    /// // 4 bytes at 1 MHz take 32us, clocked after CS falls and before it
+    /// // rises
+    /// assert_eq!(cs, [100_000, 132_000]);
+    /// assert_eq!(sclk.len(), 64);
+    /// assert!(sclk.iter().all(|time| (100_000..=132_000).contains(time)));
+    /// ```
+    pub fn write_vcd<W: Write>(&self, mut writer: W, options: VcdOptions) -> io::Result<()> {
+        let events = self.events();
+        let mut pins: Vec<String> = Vec::new();
+        let mut changes = Changes::new();
+        let mut speed = options.clock_speed;
+        // The time of the latest change, before which no event is placed
+        let mut floor = 0;
+
+        for event in events.iter() {
+            match event {
+                CaptureEvent::Transfer { time, tx, rx } => {
+                    floor = bits(
+                        &mut changes,
+                        &options,
+                        speed,
+                        floor,
+                        nanos(time),
+                        tx,
+                        Some(rx),
+                    )
+                }
+                CaptureEvent::Error { time, tx } => {
+                    floor = bits(&mut changes, &options, speed, floor, nanos(time), tx, None)
+                }
+                CaptureEvent::Edge { time, pin, high } => {
+                    let index = pins.iter().position(|name| name == pin).unwrap_or_else(|| {
+                        pins.push(pin.clone());
+                        pins.len() - 1
+                    });
+
+                    floor = floor.max(nanos(time));
+                    changes.push((floor, PINS + index, Value::Bit(Some(*high))));
+                }
+                CaptureEvent::ClockSpeed { time, speed: new } => {
+                    speed = *new;
+                    floor = floor.max(nanos(time));
+                    changes.push((floor, CLOCK_SPEED, Value::Integer(*new)));
+                }
+            }
+        }
+
+        changes.sort_by_key(|(time, _, _)| *time);
+
+        writeln!(writer, "$version rpio-utils $end")?;
+        writeln!(writer, "$timescale 1ns $end")?;
+        writeln!(writer, "$scope module spi $end")?;
+        writeln!(writer, "$var wire 1 {} sclk $end", id(SCLK))?;
+        writeln!(writer, "$var wire 1 {} mosi $end", id(MOSI))?;
+        writeln!(writer, "$var wire 1 {} miso $end", id(MISO))?;
+        writeln!(
+            writer,
+            "$var integer 32 {} clock_speed $end",
+            id(CLOCK_SPEED)
+        )?;
+        for (index, pin) in pins.iter().enumerate() {
+            writeln!(
+                writer,
+                "$var wire 1 {} {} $end",
+                id(PINS + index),
+                reference(pin)
+            )?;
+        }
+        writeln!(writer, "$upscope $end")?;
+        writeln!(writer, "$enddefinitions $end")?;
+
+        let mut values = vec![Value::Bit(None); PINS + pins.len()];
+        values[SCLK] = Value::Bit(Some(options.mode.polarity == Polarity::IdleHigh));
+        values[CLOCK_SPEED] = Value::Integer(options.clock_speed);
+
+        writeln!(writer, "#0")?;
+        writeln!(writer, "$dumpvars")?;
+        for (signal, value) in values.iter().enumerate() {
+            write_value(&mut writer, signal, *value)?;
+        }
+        writeln!(writer, "$end")?;
+
+        let mut current = 0;
+        for (time, signal, value) in changes {
+            if values[signal] == value {
+                continue;
+            }
+
+            if time != current {
+                writeln!(writer, "#{}", time)?;
+                current = time;
+            }
+
+            values[signal] = value;
+            write_value(&mut writer, signal, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Save the events to a Value Change Dump file.
+    pub fn save_vcd<P: AsRef<Path>>(&self, path: P, options: VcdOptions) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_vcd(&mut writer, options)?;
+        writer.flush()
+    }
+}
+
+/// Reconstruct the bus signals of a transfer ending at `end`, or starting at
+/// `floor` if it would start earlier. Returns the time the transfer ends.
+fn bits(
+    changes: &mut Changes,
+    options: &VcdOptions,
+    speed: u32,
+    floor: u64,
+    end: u64,
+    tx: &[u8],
+    rx: Option<&[u8]>,
+) -> u64 {
+    let half = (500_000_000 / u64::from(speed.max(1))).max(1);
+    let duration = tx.len() as u64 * 16 * half;
+    let start = end.saturating_sub(duration).max(floor);
+    let idle = options.mode.polarity == Polarity::IdleHigh;
+
+    for (index, word) in tx.iter().enumerate() {
+        for bit in 0..8 {
+            let shift = match options.bit_order {
+                BitOrder::MsbFirst => 7 - bit,
+                BitOrder::LsbFirst => bit,
+            };
+            let time = start + (index as u64 * 8 + bit as u64) * 2 * half;
+            let mosi = Some(word >> shift & 1 == 1);
+            let miso = rx.map(|rx| rx.get(index).copied().unwrap_or(0x00) >> shift & 1 == 1);
+
+            let (data, leading, trailing) = match options.mode.phase {
+                Phase::CaptureOnFirstTransition => (time, time + half, time + 2 * half),
+                Phase::CaptureOnSecondTransition => (time, time, time + half),
+            };
+
+            changes.push((data, MOSI, Value::Bit(mosi)));
+            changes.push((data, MISO, Value::Bit(miso)));
+            changes.push((leading, SCLK, Value::Bit(Some(!idle))));
+            changes.push((trailing, SCLK, Value::Bit(Some(idle))));
+        }
+    }
+
+    start + duration
+}
+
+fn nanos(time: &core::time::Duration) -> u64 {
+    time.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+fn write_value<W: Write>(writer: &mut W, signal: usize, value: Value) -> io::Result<()> {
+    match value {
+        Value::Bit(None) => writeln!(writer, "x{}", id(signal)),
+        Value::Bit(Some(true)) => writeln!(writer, "1{}", id(signal)),
+        Value::Bit(Some(false)) => writeln!(writer, "0{}", id(signal)),
+        Value::Integer(value) => writeln!(writer, "b{:b} {}", value, id(signal)),
+    }
+}
+
+/// The identifier code of a signal, from the printable ASCII characters.
+fn id(mut signal: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push(char::from(b'!' + (signal % 94) as u8));
+        signal /= 94;
+
+        if signal == 0 {
+            return id;
+        }
+
+        signal -= 1;
+    }
+}
+
+/// A pin name usable as a reference, without whitespace.
+fn reference(name: &str) -> String {
+    match name.trim().is_empty() {
+        true => "pin".to_owned(),
+        false => name.split_whitespace().collect::<Vec<_>>().join("_"),
+    }
+}
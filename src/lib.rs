@@ -7,7 +7,7 @@
 //!
 //! # Examples
 //!
-//! ```
+//! ```ignore
 //! use rpio_utils::*;
 //!
 //! let real_spi = ...;
@@ -24,10 +24,109 @@
 //! // Transfer handles the chip select automatically in both cases:
 //! let message = [0x01, 0x02, 0x03, 0x04];
 //! let res: &[u8] = spi.transfer(&mut message).unwrap();
+//!
+//! // Or run several operations while the chip is selected:
+//! let mut response = [0; 4];
+//! spi.transaction(&mut [
+//!     Operation::Write(&[0x03, 0x00]),
+//!     Operation::DelayUs(10),
+//!     Operation::Read(&mut response),
+//! ])
+//! .unwrap();
+//! ```
+//!
+//! # Shared buses
+//!
+//! Several chips on one bus each get their own handle from a [`SharedBus`]:
+//!
+//! ```ignore
+//! let bus = SharedBus::ref_cell(Transport::hal(real_spi).init());
+//!
+//! let mut adc = bus.device(adc_cs_pin).with_clock_speed(1_000_000).init();
+//! let mut flash = bus.device(flash_cs_pin).init();
+//! ```
+//!
+//! With the `critical-section` or `std` features, the bus can also be
+//! locked with [`SharedBus::critical_section`] or [`SharedBus::mutex`].
+//!
+//! # Bit-banged SPI
+//!
+//! Without an SPI peripheral, any GPIO pins can drive the bus in software:
+//!
+//! ```ignore
+//! let spi = Transport::bitbang(sck_pin, mosi_pin, miso_pin, delay)
+//!     .with_mode(MODE_3)
+//!     .with_cs(cs_pin)
+//!     .init();
+//! ```
+//!
+//! The half period defaults to 5us and follows
+//! [`set_clock_speed`](SpiDev::set_clock_speed).
+//!
+//! # Registers
+//!
+//! A [`RegisterInterface`] reads and writes chip registers through any
+//! [`SpiDev`], and [`register_map!`] declares them as typed values:
+//!
+//! ```ignore
+//! let mut regs = RegisterInterface::builder(spi).with_register_bytes(2).init();
+//! regs.modify_reg(0x20, |value| value | 0x0001)?;
+//! ```
+//!
+//! # embedded-hal 1.0
+//!
+//! With the `eh1` feature, every transport which keeps the chip selected
+//! throughout a transaction also implements the 1.0 `SpiDevice` trait: those
+//! with a chip select pin, devices on a [`SharedBus`] and
+//! [`Transport::rppal`] without a pin, which groups the transaction for
+//! spidev. The `DelayNs` operations of a transaction wait on the delay of
+//! the transport, so [`Transport::hal`] and `Transport::rp2040` with a chip
+//! select pin need one from `with_timing`, and fail with
+//! [`NotImplemented`](ErrorKind::NotImplemented) without it. 1.0 buses and
+//! pins can be wrapped in [`Eh1`] to construct a transport:
+//!
+//! ```ignore
+//! let spi = Transport::hal(Eh1(real_spi_bus))
+//!     .with_cs(Eh1(real_cs_pin))
+//!     .init();
+//! ```
+//!
+//! # Async
+//!
+//! With the `async` feature, an `embedded-hal-async` bus can be used to
+//! construct a transport implementing [`AsyncSpiDev`]:
+//!
+//! ```ignore
+//! let mut spi = Transport::hal_async(real_async_spi_bus)
+//!     .with_cs(real_cs_pin)
+//!     .with_delay(real_async_delay)
+//!     .init();
+//!
+//! spi.transfer(&mut message).await.unwrap();
 //! ```
+pub mod register;
 mod transport;
-pub use embedded_hal::{blocking::spi::Transfer, digital::v2::OutputPin, spi::Polarity};
-pub use transport::{ChipSelect, ClockSpeed, Error, SpiDev, Transport};
+pub use embedded_hal::{
+    blocking::spi::Transfer,
+    digital::v2::{InputPin, OutputPin},
+    spi::{Mode, Polarity},
+};
+pub use register::{Endianness, RegisterInterface};
+pub use transport::{
+    shared::{self, SharedBus},
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, DeviceError, Error, ErrorKind, NoDelay,
+    Operation, Resume, SelectGuard, Selected, SpiDev, SpiModeControl, Timing, Transport,
+    WordSizeControl,
+};
+
+#[cfg(feature = "hal")]
+pub use transport::BitbangError;
+
+#[cfg(feature = "eh1")]
+pub use transport::Eh1;
+
+#[cfg(feature = "async")]
+pub use transport::AsyncSpiDev;
 
 #[cfg(feature = "std")]
 extern crate std;
@@ -0,0 +1,69 @@
+use super::{Endianness, Layout, RegisterInterface};
+use crate::SpiDev;
+
+pub struct RegisterBuilder<S: SpiDev> {
+    spi: S,
+    layout: Layout,
+}
+
+impl<S: SpiDev> RegisterBuilder<S> {
+    pub(crate) fn new(spi: S) -> Self {
+        Self {
+            spi,
+            layout: Layout::new(),
+        }
+    }
+
+    /// Use the provided layout, replacing any settings made so far.
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// See [`Layout::with_address_bytes`].
+    pub fn with_address_bytes(mut self, count: usize) -> Self {
+        self.layout = self.layout.with_address_bytes(count);
+        self
+    }
+
+    /// See [`Layout::with_register_bytes`].
+    pub fn with_register_bytes(mut self, count: usize) -> Self {
+        self.layout = self.layout.with_register_bytes(count);
+        self
+    }
+
+    /// See [`Layout::with_read_bit`].
+    pub fn with_read_bit(mut self, bit: u32) -> Self {
+        self.layout = self.layout.with_read_bit(bit);
+        self
+    }
+
+    /// See [`Layout::with_write_bit`].
+    pub fn with_write_bit(mut self, bit: u32) -> Self {
+        self.layout = self.layout.with_write_bit(bit);
+        self
+    }
+
+    /// See [`Layout::with_auto_increment`].
+    pub fn with_auto_increment(mut self, bit: u32) -> Self {
+        self.layout = self.layout.with_auto_increment(bit);
+        self
+    }
+
+    /// See [`Layout::with_dummy_bytes`].
+    pub fn with_dummy_bytes(mut self, count: usize) -> Self {
+        self.layout = self.layout.with_dummy_bytes(count);
+        self
+    }
+
+    /// See [`Layout::with_endianness`].
+    pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.layout = self.layout.with_endianness(endianness);
+        self
+    }
+
+    /// Initialize the register interface.
+    pub fn init(self) -> RegisterInterface<S> {
+        RegisterInterface::new(self.spi, self.layout)
+    }
+}
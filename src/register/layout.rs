@@ -0,0 +1,207 @@
+/// Largest number of address bytes.
+pub const MAX_ADDRESS_BYTES: usize = 4;
+
+/// Largest number of dummy (turnaround) bytes.
+pub const MAX_DUMMY_BYTES: usize = 8;
+
+/// Largest register width in bytes.
+pub const MAX_REGISTER_BYTES: usize = 4;
+
+pub(crate) const MAX_HEADER_LEN: usize = MAX_ADDRESS_BYTES + MAX_DUMMY_BYTES;
+
+/// The byte order of multi-byte register values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Endianness {
+    /// Most significant byte first.
+    #[default]
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+/// How register accesses are framed on the bus.
+///
+/// The address is sent most significant byte first. Bit positions count
+/// from the least significant bit of the address, so the default read bit
+/// of 7 is the top bit of a single address byte. Dummy bytes are clocked
+/// between the address and the data of reads only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub(crate) address_bytes: usize,
+    pub(crate) register_bytes: usize,
+    pub(crate) rw_bit: u32,
+    pub(crate) read_high: bool,
+    pub(crate) increment_bit: Option<u32>,
+    pub(crate) dummy_bytes: usize,
+    pub(crate) endianness: Endianness,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    /// One address byte with the read bit at bit 7, one byte registers and
+    /// no dummy bytes.
+    pub const fn new() -> Self {
+        Self {
+            address_bytes: 1,
+            register_bytes: 1,
+            rw_bit: 7,
+            read_high: true,
+            increment_bit: None,
+            dummy_bytes: 0,
+            endianness: Endianness::Big,
+        }
+    }
+
+    /// Send addresses as this many bytes, from 1 up to
+    /// [`MAX_ADDRESS_BYTES`]. Defaults to 1.
+    pub const fn with_address_bytes(mut self, count: usize) -> Self {
+        self.address_bytes = clamp(count, MAX_ADDRESS_BYTES);
+        self
+    }
+
+    /// Registers hold this many bytes, from 1 up to [`MAX_REGISTER_BYTES`].
+    /// Defaults to 1.
+    pub const fn with_register_bytes(mut self, count: usize) -> Self {
+        self.register_bytes = clamp(count, MAX_REGISTER_BYTES);
+        self
+    }
+
+    /// Set this address bit to read and clear it to write. Defaults to bit 7.
+    ///
+    /// # Panics
+    ///
+    /// If the bit is beyond the largest address, bit 31.
+    pub const fn with_read_bit(mut self, bit: u32) -> Self {
+        self.rw_bit = address_bit(bit);
+        self.read_high = true;
+        self
+    }
+
+    /// Set this address bit to write and clear it to read.
+    ///
+    /// # Panics
+    ///
+    /// If the bit is beyond the largest address, bit 31.
+    pub const fn with_write_bit(mut self, bit: u32) -> Self {
+        self.rw_bit = address_bit(bit);
+        self.read_high = false;
+        self
+    }
+
+    /// Set this address bit when accessing more than one byte, so the chip
+    /// increments the address. By default no bit is set.
+    ///
+    /// # Panics
+    ///
+    /// If the bit is beyond the largest address, bit 31.
+    pub const fn with_auto_increment(mut self, bit: u32) -> Self {
+        self.increment_bit = Some(address_bit(bit));
+        self
+    }
+
+    /// Clock this many bytes between the address and the data of a read, up
+    /// to [`MAX_DUMMY_BYTES`]. Defaults to 0.
+    pub const fn with_dummy_bytes(mut self, count: usize) -> Self {
+        self.dummy_bytes = match count > MAX_DUMMY_BYTES {
+            true => MAX_DUMMY_BYTES,
+            false => count,
+        };
+        self
+    }
+
+    /// Use the provided byte order for register values. Defaults to
+    /// [Big](Endianness::Big).
+    pub const fn with_endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Write the address and any dummy bytes into `buffer`, returning their
+    /// length.
+    pub(crate) fn header(
+        &self,
+        address: u32,
+        read: bool,
+        data_len: usize,
+        buffer: &mut [u8],
+    ) -> usize {
+        let mut address = address;
+
+        if read == self.read_high {
+            address |= 1 << self.rw_bit;
+        } else {
+            address &= !(1 << self.rw_bit);
+        }
+
+        if let Some(bit) = self.increment_bit.filter(|_| data_len > 1) {
+            address |= 1 << bit;
+        }
+
+        let bytes = address.to_be_bytes();
+        buffer[..self.address_bytes].copy_from_slice(&bytes[4 - self.address_bytes..]);
+        buffer[self.address_bytes..self.address_bytes + self.dummy_bytes].fill(0x00);
+
+        match read {
+            true => self.address_bytes + self.dummy_bytes,
+            false => self.address_bytes,
+        }
+    }
+
+    /// Split an address written by [`header`](Self::header) into the
+    /// register address and whether it is a read.
+    #[cfg(feature = "dev")]
+    pub(crate) fn parse_header(&self, words: &[u8]) -> Option<(u32, bool)> {
+        let header = words
+            .get(..self.address_bytes)?
+            .iter()
+            .fold(0u32, |value, word| (value << 8) | u32::from(*word));
+        let read = ((header >> self.rw_bit) & 1 == 1) == self.read_high;
+        let mut address = header & !(1 << self.rw_bit);
+
+        if let Some(bit) = self.increment_bit {
+            address &= !(1 << bit);
+        }
+
+        Some((address, read))
+    }
+
+    pub(crate) fn encode(&self, value: u32, words: &mut [u8]) {
+        let len = words.len();
+
+        match self.endianness {
+            Endianness::Big => words.copy_from_slice(&value.to_be_bytes()[4 - len..]),
+            Endianness::Little => words.copy_from_slice(&value.to_le_bytes()[..len]),
+        }
+    }
+
+    pub(crate) fn decode(&self, words: &[u8]) -> u32 {
+        let fold = |value: u32, word: &u8| (value << 8) | u32::from(*word);
+
+        match self.endianness {
+            Endianness::Big => words.iter().fold(0, fold),
+            Endianness::Little => words.iter().rev().fold(0, fold),
+        }
+    }
+}
+
+const fn clamp(count: usize, max: usize) -> usize {
+    match count {
+        0 => 1,
+        count if count > max => max,
+        count => count,
+    }
+}
+
+/// Check that the bit lies within the largest address.
+const fn address_bit(bit: u32) -> u32 {
+    assert!(
+        bit < (MAX_ADDRESS_BYTES * 8) as u32,
+        "address bit beyond the largest address"
+    );
+    bit
+}
@@ -0,0 +1,222 @@
+/// Whether a register can be read, written or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+/// A register value, usually declared with
+/// [`register_map!`](crate::register_map).
+pub trait Register: Copy {
+    /// The address of the register.
+    const ADDRESS: u32;
+    /// Whether the register can be read, written or both.
+    const ACCESS: Access;
+
+    /// Wrap the raw value.
+    fn from_bits(bits: u32) -> Self;
+
+    /// Unwrap the raw value.
+    fn bits(self) -> u32;
+}
+
+/// Indicates that the register can be read.
+pub trait Readable: Register {}
+
+/// Indicates that the register can be written.
+pub trait Writable: Register {}
+
+/// Declare the registers of a chip as a module of typed values.
+///
+/// Each register is declared with its address, access (`RO`, `WO` or `RW`)
+/// and bitfields. A bitfield names a getter and a setter, followed by its
+/// lowest bit and width. The module contains:
+///
+/// - A [`Register`] type per register, implementing [`Readable`] and/or
+///   [`Writable`], so writing a read-only register does not compile.
+/// - `LAYOUT`, the [`Layout`](crate::register::Layout) of the chip, which
+///   defaults to [`Layout::new`](crate::register::Layout::new).
+/// - `interface(spi)`, which creates a
+///   [`RegisterInterface`](crate::RegisterInterface) using `LAYOUT`.
+/// - With the `dev` feature, `model()`, which creates a
+///   [`RegisterModel`](crate::dev::RegisterModel) holding the same
+///   registers.
+///
+/// ```
+/// use rpio_utils::{register::Layout, *};
+///
+/// register_map! {
+///     /// An accelerometer.
+///     pub mod accel {
+///         layout: Layout::new().with_auto_increment(6);
+///
+///         /// Identifies the chip.
+///         0x0f => WhoAmI: RO {
+///             id, with_id: 0, 8;
+///         }
+///         0x20 => Ctrl1: RW {
+///             rate, with_rate: 4, 4;
+///             enable, with_enable: 0, 1;
+///         }
+///         0x21 => Ctrl2: RW {
+///             scale, with_scale: 4, 2;
+///         }
+///     }
+/// }
+///
+/// fn main() {
+/// # #[cfg(feature = "dev")] {
+///     use rpio_utils::dev::*;
+///
+///     let model = accel::model();
+///     model.set_reg(accel::WhoAmI(0x33));
+///     let (spi, _) = Mock::spi("Accel").without_log().with_model(model.clone()).init();
+///
+///     let mut regs = accel::interface(Transport::hal(spi).init());
+///     assert_eq!(regs.read::<accel::WhoAmI>().unwrap().id(), 0x33);
+///
+///     regs.modify(|ctrl: accel::Ctrl1| ctrl.with_rate(5).with_enable(1)).unwrap();
+///     assert_eq!(model.get_reg::<accel::Ctrl1>(), accel::Ctrl1(0x51));
+///
+///     // A burst reaches the model as a single frame
+///     regs.write_burst(0x20, &[0x37, 0x10]).unwrap();
+///     assert_eq!(model.get_reg::<accel::Ctrl2>().scale(), 1);
+///     let mut data = [0; 2];
+///     regs.read_burst(0x20, &mut data).unwrap();
+///     assert_eq!(data, [0x37, 0x10]);
+/// # }
+/// }
+/// ```
+///
+/// Writing a read-only register does not compile:
+///
+/// ```compile_fail
+/// use rpio_utils::*;
+///
+/// register_map! {
+///     pub mod accel {
+///         0x0f => WhoAmI: RO {
+///             id, with_id: 0, 8;
+///         }
+///     }
+/// }
+///
+/// fn clear<S: SpiDev>(regs: &mut RegisterInterface<S>) {
+///     regs.write(accel::WhoAmI(0x00)).ok();
+/// }
+///
+/// fn main() {}
+/// ```
+#[macro_export]
+macro_rules! register_map {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $map:ident {
+            $(layout: $layout:expr;)?
+
+            $(
+                $(#[$reg_meta:meta])*
+                $address:literal => $reg:ident: $access:ident {
+                    $(
+                        $(#[$field_meta:meta])*
+                        $field:ident, $setter:ident: $offset:literal, $width:literal;
+                    )*
+                }
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $map {
+            #[allow(unused_imports)]
+            use super::*;
+
+            /// The layout of register accesses.
+            pub const LAYOUT: $crate::register::Layout =
+                $crate::register_map!(@layout $($layout)?);
+
+            /// Create a register interface using [`LAYOUT`].
+            pub fn interface<S: $crate::SpiDev>(spi: S) -> $crate::RegisterInterface<S> {
+                $crate::RegisterInterface::builder(spi).with_layout(LAYOUT).init()
+            }
+
+            $crate::__register_model!($($reg),*);
+
+            $(
+                $(#[$reg_meta])*
+                #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+                pub struct $reg(pub u32);
+
+                impl $reg {
+                    $(
+                        $(#[$field_meta])*
+                        pub fn $field(&self) -> u32 {
+                            (self.0 >> $offset) & $crate::register_map!(@mask $width)
+                        }
+
+                        /// Replace the bitfield, ignoring bits beyond its width.
+                        pub fn $setter(self, value: u32) -> Self {
+                            let mask = $crate::register_map!(@mask $width) << $offset;
+                            Self((self.0 & !mask) | ((value << $offset) & mask))
+                        }
+                    )*
+                }
+
+                impl $crate::register::Register for $reg {
+                    const ADDRESS: u32 = $address;
+                    const ACCESS: $crate::register::Access =
+                        $crate::register_map!(@access $access);
+
+                    fn from_bits(bits: u32) -> Self {
+                        Self(bits)
+                    }
+
+                    fn bits(self) -> u32 {
+                        self.0
+                    }
+                }
+
+                $crate::register_map!(@impl $access $reg);
+            )*
+        }
+    };
+
+    (@layout) => { $crate::register::Layout::new() };
+    (@layout $layout:expr) => { $layout };
+
+    (@mask $width:literal) => { (((1u64 << $width) - 1) as u32) };
+
+    (@access RO) => { $crate::register::Access::ReadOnly };
+    (@access WO) => { $crate::register::Access::WriteOnly };
+    (@access RW) => { $crate::register::Access::ReadWrite };
+
+    (@impl RO $reg:ident) => {
+        impl $crate::register::Readable for $reg {}
+    };
+    (@impl WO $reg:ident) => {
+        impl $crate::register::Writable for $reg {}
+    };
+    (@impl RW $reg:ident) => {
+        impl $crate::register::Readable for $reg {}
+        impl $crate::register::Writable for $reg {}
+    };
+}
+
+#[cfg(feature = "dev")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_model {
+    ($($reg:ident),*) => {
+        /// Create a mock device holding these registers, each starting at 0.
+        pub fn model() -> $crate::dev::RegisterModel {
+            $crate::dev::RegisterModel::new(LAYOUT)$(.with_register::<$reg>())*
+        }
+    };
+}
+
+#[cfg(not(feature = "dev"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_model {
+    ($($reg:ident),*) => {};
+}
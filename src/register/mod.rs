@@ -0,0 +1,125 @@
+mod build;
+mod layout;
+mod map;
+
+pub use build::RegisterBuilder;
+pub use layout::{Endianness, Layout, MAX_ADDRESS_BYTES, MAX_DUMMY_BYTES, MAX_REGISTER_BYTES};
+pub use map::{Access, Readable, Register, Writable};
+
+use crate::{transport::Result, Operation, SpiDev};
+use layout::MAX_HEADER_LEN;
+
+/// Reads and writes the registers of a chip which frames each access as an
+/// address, carrying a read/write bit, followed by data. The framing is
+/// described by a [`Layout`].
+///
+/// Single register accesses are one [`transfer`](crate::Transfer::transfer).
+/// Bursts are a [`transaction`](SpiDev::transaction), so when chip selection
+/// is not controlled by a pin the transport must group the operations
+/// itself, as the hal, rp2040 and rppal transports do.
+///
+/// ```ignore
+/// let mut regs = RegisterInterface::builder(spi)
+///     .with_register_bytes(2)
+///     .with_auto_increment(6)
+///     .init();
+///
+/// let id = regs.read_reg(0x0f)?;
+/// regs.modify_reg(0x20, |value| value | 0x0001)?;
+/// ```
+#[derive(Debug)]
+pub struct RegisterInterface<S: SpiDev> {
+    spi: S,
+    layout: Layout,
+}
+
+impl<S: SpiDev> RegisterInterface<S> {
+    /// Configure a register interface on top of the transport.
+    pub fn builder(spi: S) -> RegisterBuilder<S> {
+        RegisterBuilder::new(spi)
+    }
+
+    pub(crate) fn new(spi: S, layout: Layout) -> Self {
+        Self { spi, layout }
+    }
+
+    /// Release the transport.
+    pub fn into_inner(self) -> S {
+        self.spi
+    }
+
+    /// Read the value of a register.
+    pub fn read_reg(&mut self, address: u32) -> Result<u32, S::Source> {
+        let layout = self.layout;
+        let mut buffer = [0u8; MAX_HEADER_LEN + MAX_REGISTER_BYTES];
+        let header_len = layout.header(address, true, layout.register_bytes, &mut buffer);
+        let words = &mut buffer[..header_len + layout.register_bytes];
+
+        self.spi.transfer(words)?;
+        Ok(layout.decode(&words[header_len..]))
+    }
+
+    /// Write the value of a register. Bits beyond the register width are
+    /// ignored.
+    pub fn write_reg(&mut self, address: u32, value: u32) -> Result<(), S::Source> {
+        let layout = self.layout;
+        let mut buffer = [0u8; MAX_HEADER_LEN + MAX_REGISTER_BYTES];
+        let header_len = layout.header(address, false, layout.register_bytes, &mut buffer);
+        let words = &mut buffer[..header_len + layout.register_bytes];
+
+        layout.encode(value, &mut words[header_len..]);
+        self.spi.transfer(words).and(Ok(()))
+    }
+
+    /// Read a register, then write back the value returned by `f`.
+    pub fn modify_reg<F: FnOnce(u32) -> u32>(
+        &mut self,
+        address: u32,
+        f: F,
+    ) -> Result<(), S::Source> {
+        let value = self.read_reg(address)?;
+        self.write_reg(address, f(value))
+    }
+
+    /// Read consecutive bytes starting at the register.
+    pub fn read_burst(&mut self, address: u32, words: &mut [u8]) -> Result<(), S::Source> {
+        let mut header = [0u8; MAX_HEADER_LEN];
+        let header_len = self.layout.header(address, true, words.len(), &mut header);
+
+        self.spi.transaction(&mut [
+            Operation::Write(&header[..header_len]),
+            Operation::Read(words),
+        ])
+    }
+
+    /// Write consecutive bytes starting at the register.
+    pub fn write_burst(&mut self, address: u32, words: &[u8]) -> Result<(), S::Source> {
+        let mut header = [0u8; MAX_HEADER_LEN];
+        let header_len = self.layout.header(address, false, words.len(), &mut header);
+
+        self.spi.transaction(&mut [
+            Operation::Write(&header[..header_len]),
+            Operation::Write(words),
+        ])
+    }
+
+    /// Read a register declared with [`register_map!`](crate::register_map).
+    pub fn read<R: Readable>(&mut self) -> Result<R, S::Source> {
+        self.read_reg(R::ADDRESS).map(R::from_bits)
+    }
+
+    /// Write a register declared with [`register_map!`](crate::register_map).
+    pub fn write<R: Writable>(&mut self, value: R) -> Result<(), S::Source> {
+        self.write_reg(R::ADDRESS, value.bits())
+    }
+
+    /// Read a register declared with [`register_map!`](crate::register_map),
+    /// then write back the value returned by `f`.
+    pub fn modify<R: Readable + Writable, F: FnOnce(R) -> R>(
+        &mut self,
+        f: F,
+    ) -> Result<(), S::Source> {
+        let value = self.read::<R>()?;
+        self.write(f(value))
+    }
+}
@@ -0,0 +1,170 @@
+use super::{ErrorKind, Operation, Result};
+
+#[cfg(not(feature = "std"))]
+use super::transaction::SCRATCH_LEN;
+
+/// The async counterpart of [`SpiDev`](super::SpiDev). Transfers exchange
+/// bytes in place:
+///
+/// - Selects the chip at the start of transfer.
+/// - Deselects the chip at the end of successful transfer.
+/// - Uses the [`Error`](super::Error) type, with the error of the
+///   underlying bus or pins as its [`Source`](Self::Source).
+#[allow(async_fn_in_trait)]
+pub trait AsyncSpiDev {
+    /// The error of the underlying bus or pins, kept in each
+    /// [`Error`](super::Error).
+    type Source;
+
+    /// Exchange bytes with the chip, handling chip selection.
+    async fn transfer(&mut self, words: &mut [u8]) -> Result<(), Self::Source>;
+
+    /// Whether chip selection can be controlled
+    fn is_chip_select(&self) -> bool {
+        false
+    }
+
+    /// Whether clock speed can be controlled
+    fn is_clock_speed(&self) -> bool {
+        false
+    }
+
+    /// The most bytes exchanged with the device at once, if limited.
+    /// Transports which control chip selection split longer transfers into
+    /// chunks while the chip stays selected.
+    fn max_transfer_len(&self) -> Option<usize> {
+        None
+    }
+
+    /// Select the chip.
+    async fn select(&mut self) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Deselect the chip.
+    async fn deselect(&mut self) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Exchange bytes with the chip without selecting or deslecting it.
+    async fn raw_transfer(&mut self, _words: &mut [u8]) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Exchange bytes with the chip without selecting it. Deselect only if an
+    /// error occurs during the transfer.
+    async fn raw_transfer_or_deselect(&mut self, words: &mut [u8]) -> Result<(), Self::Source> {
+        match self.raw_transfer(words).await {
+            Err(err) => Err(err.after_deselect(self.deselect().await)),
+            ok => ok,
+        }
+    }
+
+    /// Set the SPI clock speed.
+    async fn set_clock_speed(&mut self, _speed: u32) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Wait for the given number of microseconds.
+    async fn delay_us(&mut self, _us: u32) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Run the operations in order while the chip is selected. If an error
+    /// occurs, the chip is deselected and the remaining operations are
+    /// skipped. Errors count the bytes exchanged by the whole transaction.
+    ///
+    /// When chip selection cannot be controlled, each operation is a
+    /// separate [`transfer`](AsyncSpiDev::transfer).
+    async fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Self::Source> {
+        let chip_select = self.is_chip_select();
+
+        if chip_select {
+            self.select().await?;
+        }
+
+        let mut done = 0;
+
+        for operation in operations.iter_mut() {
+            if let Err(err) = run(self, chip_select, operation).await {
+                let err = err.offset(done);
+                return match chip_select {
+                    true => Err(err.after_deselect(self.deselect().await)),
+                    false => Err(err),
+                };
+            }
+
+            done += operation.len();
+        }
+
+        match chip_select {
+            true => self.deselect().await,
+            false => Ok(()),
+        }
+    }
+}
+
+/// Run a single operation, using raw transfers if the chip is selected.
+async fn run<S: AsyncSpiDev + ?Sized>(
+    spi: &mut S,
+    chip_select: bool,
+    operation: &mut Operation<'_>,
+) -> Result<(), S::Source> {
+    match operation {
+        Operation::Write(words) => write(spi, chip_select, words).await,
+        Operation::Read(words) => {
+            words.fill(0x00);
+            exchange(spi, chip_select, words).await
+        }
+        Operation::Transfer(words) => exchange(spi, chip_select, words).await,
+        Operation::DelayUs(us) => spi.delay_us(*us).await,
+    }
+}
+
+/// Clock out the bytes of a write in a single exchange, like
+/// [`copied`](super::transaction::copied).
+#[cfg(feature = "std")]
+async fn write<S: AsyncSpiDev + ?Sized>(
+    spi: &mut S,
+    chip_select: bool,
+    words: &[u8],
+) -> Result<(), S::Source> {
+    exchange(spi, chip_select, &mut words.to_vec()).await
+}
+
+/// Clock out the bytes of a write through a stack buffer, which is only
+/// possible for longer writes while the chip stays selected, like
+/// [`copied`](super::transaction::copied).
+#[cfg(not(feature = "std"))]
+async fn write<S: AsyncSpiDev + ?Sized>(
+    spi: &mut S,
+    chip_select: bool,
+    words: &[u8],
+) -> Result<(), S::Source> {
+    if !chip_select && words.len() > SCRATCH_LEN {
+        return Err(ErrorKind::NotImplemented.into());
+    }
+
+    let mut scratch = [0u8; SCRATCH_LEN];
+
+    for (index, chunk) in words.chunks(SCRATCH_LEN).enumerate() {
+        let scratch = &mut scratch[..chunk.len()];
+        scratch.copy_from_slice(chunk);
+        exchange(spi, chip_select, scratch)
+            .await
+            .map_err(|err| err.offset(index * SCRATCH_LEN))?;
+    }
+
+    Ok(())
+}
+
+async fn exchange<S: AsyncSpiDev + ?Sized>(
+    spi: &mut S,
+    chip_select: bool,
+    words: &mut [u8],
+) -> Result<(), S::Source> {
+    match chip_select {
+        true => spi.raw_transfer(words).await,
+        false => spi.transfer(words).await,
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Error, Result};
+use crate::Transfer;
+
+/// The order in which the bits of each byte are clocked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    #[default]
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Exchange bytes with `spi`, which clocks the most significant bit first,
+/// reversing the bits of each byte in software for
+/// [LsbFirst](BitOrder::LsbFirst). `map_err` keeps the error of `spi` as the
+/// source.
+///
+/// The software order relies on `spi` exchanging the bytes in place.
+pub(crate) fn transfer_with<'w, S, E, F>(
+    spi: &mut S,
+    order: BitOrder,
+    words: &'w mut [u8],
+    map_err: F,
+) -> Result<&'w [u8], E>
+where
+    S: Transfer<u8>,
+    F: FnOnce(S::Error) -> Error<E>,
+{
+    match order {
+        BitOrder::MsbFirst => spi.transfer(words).map_err(map_err),
+        BitOrder::LsbFirst => {
+            reverse_bits(words);
+            let result = spi.transfer(words).map(|_| ()).map_err(map_err);
+            reverse_bits(words);
+            result.and(Ok(words))
+        }
+    }
+}
+
+fn reverse_bits(words: &mut [u8]) {
+    words
+        .iter_mut()
+        .for_each(|word| *word = word.reverse_bits());
+}
@@ -0,0 +1,61 @@
+use super::super::{chunk, Error, Result};
+use super::{bus::Bus, BitbangError};
+use crate::{
+    BitOrder, BitOrderControl, ClockSpeed, InputPin, Mode, OutputPin, SpiDev, SpiModeControl,
+    Transfer,
+};
+use embedded_hal::blocking::delay::DelayUs;
+
+pub struct Transport<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> {
+    bus: Bus<SCK, MOSI, MISO, D>,
+    max_chunk: Option<usize>,
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>>
+    Transport<SCK, MOSI, MISO, D>
+{
+    pub fn new(bus: Bus<SCK, MOSI, MISO, D>, max_chunk: Option<usize>) -> Self {
+        Self { bus, max_chunk }
+    }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> Transfer<u8>
+    for Transport<SCK, MOSI, MISO, D>
+{
+    type Error = Error<<Self as SpiDev>::Source>;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], <Self as SpiDev>::Source> {
+        chunk::check_len(words.len(), self.max_chunk)?;
+        self.bus.exchange(words)?;
+        Ok(words)
+    }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> SpiDev
+    for Transport<SCK, MOSI, MISO, D>
+{
+    type Source = BitbangError<SCK::Error, MOSI::Error, MISO::Error>;
+
+    impl_bitbang_common!();
+
+    /// Transfers are not split, since the chip select is not handled, so
+    /// longer transfers fail with [`TooLong`](crate::ErrorKind::TooLong).
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> ClockSpeed
+    for Transport<SCK, MOSI, MISO, D>
+{
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> SpiModeControl
+    for Transport<SCK, MOSI, MISO, D>
+{
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> BitOrderControl
+    for Transport<SCK, MOSI, MISO, D>
+{
+}
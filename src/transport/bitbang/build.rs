@@ -0,0 +1,224 @@
+use super::{auto, bus::Bus, cs};
+use crate::{BitOrder, InputPin, Mode, OutputPin, Polarity, Transport};
+use embedded_hal::{blocking::delay::DelayUs, spi::MODE_0};
+
+impl Transport {
+    /// Construct a software (bit-banged) transport from GPIO pins. The delay
+    /// times each half period of the clock, and also serves
+    /// [`Operation::DelayUs`](crate::Operation::DelayUs) and the `DelayNs`
+    /// operations of embedded-hal 1.0 transactions. With [`NoDelay`](crate::NoDelay)
+    /// these do not wait.
+    ///
+    /// Each bit is driven on MOSI before the clock edge on which it is
+    /// sampled, which the SPI mode sets. Recording the pins whenever MISO is
+    /// sampled, with MISO looped back to MOSI:
+    ///
+    /// ```
+    /// # #[cfg(feature = "dev")] {
+    /// use embedded_hal::spi::{MODE_0, MODE_1, MODE_2, MODE_3};
+    /// use rpio_utils::{*, dev::*};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// // The levels of SCK, MOSI and CS at each sample
+    /// fn sample(mode: Mode, order: BitOrder, with_cs: bool) -> Vec<(bool, bool, bool)> {
+    ///     let (sck, sck_control) = Mock::pin("SCK").without_log().init();
+    ///     let (mosi, mosi_control) = Mock::pin("MOSI").without_log().init();
+    ///     let (cs, cs_control) = Mock::pin("CS").without_log().with_value(true).init();
+    ///
+    ///     let samples = Arc::new(Mutex::new(Vec::new()));
+    ///     let record = samples.clone();
+    ///     let (miso, _) = Mock::input_pin("MISO")
+    ///         .without_log()
+    ///         .with_generator(move || {
+    ///             let mosi = mosi_control.get_value();
+    ///             let sample = (sck_control.get_value(), mosi, cs_control.get_value());
+    ///             record.lock().unwrap().push(sample);
+    ///             mosi
+    ///         })
+    ///         .init();
+    ///
+    ///     let builder = Transport::bitbang(sck, mosi, miso, NoDelay)
+    ///         .with_mode(mode)
+    ///         .with_bit_order(order);
+    ///     let mut words = [0xa3];
+    ///     match with_cs {
+    ///         true => builder.with_cs(cs).init().transfer(&mut words).unwrap(),
+    ///         false => builder.init().transfer(&mut words).unwrap(),
+    ///     };
+    ///     assert_eq!(words, [0xa3]);
+    ///
+    ///     let samples = samples.lock().unwrap().clone();
+    ///     samples
+    /// }
+    ///
+    /// fn bits(samples: &[(bool, bool, bool)]) -> Vec<u8> {
+    ///     samples.iter().map(|(_, mosi, _)| u8::from(*mosi)).collect()
+    /// }
+    ///
+    /// // 0xa3, most significant bit first
+    /// let msb_first = [1, 0, 1, 0, 0, 0, 1, 1];
+    ///
+    /// // Modes 0 and 3 sample on the rising edge, 1 and 2 on the falling edge
+    /// for (mode, sck) in [(MODE_0, true), (MODE_1, false), (MODE_2, false), (MODE_3, true)] {
+    ///     let samples = sample(mode, BitOrder::MsbFirst, false);
+    ///     assert!(samples.iter().all(|(level, _, _)| *level == sck));
+    ///     assert_eq!(bits(&samples), msb_first);
+    /// }
+    ///
+    /// let samples = sample(MODE_1, BitOrder::LsbFirst, false);
+    /// assert_eq!(bits(&samples), [1, 1, 0, 0, 0, 1, 0, 1]);
+    ///
+    /// // The chip stays selected (low) for every bit
+    /// let samples = sample(MODE_3, BitOrder::MsbFirst, true);
+    /// assert!(samples.iter().all(|(sck, _, cs)| *sck && !*cs));
+    /// assert_eq!(bits(&samples), msb_first);
+    /// # }
+    /// ```
+    pub fn bitbang<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>>(
+        sck: SCK,
+        mosi: MOSI,
+        miso: MISO,
+        delay: D,
+    ) -> BitbangBuilder<SCK, MOSI, MISO, D> {
+        BitbangBuilder {
+            sck,
+            mosi,
+            miso,
+            delay,
+            mode: MODE_0,
+            bit_order: BitOrder::MsbFirst,
+            half_period_us: 5,
+            max_chunk: None,
+        }
+    }
+}
+
+pub struct BitbangBuilder<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO,
+    delay: D,
+    mode: Mode,
+    bit_order: BitOrder,
+    half_period_us: u32,
+    max_chunk: Option<usize>,
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>>
+    BitbangBuilder<SCK, MOSI, MISO, D>
+{
+    /// Use the provided SPI mode. Defaults to [`MODE_0`].
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Use the provided bit order. Defaults to [MsbFirst](BitOrder::MsbFirst).
+    pub fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Wait this long between clock edges. Defaults to 5us (100kHz).
+    pub fn with_half_period_us(mut self, half_period_us: u32) -> Self {
+        self.half_period_us = half_period_us;
+        self
+    }
+
+    /// Advertise the most bytes the device exchanges at once. Since the
+    /// chip select is not handled, longer transfers fail with
+    /// [`TooLong`](crate::ErrorKind::TooLong) rather than being split.
+    /// With a chip select pin, they are split while the chip stays selected:
+    ///
+    /// ```
+    /// # #[cfg(feature = "dev")] {
+    /// use rpio_utils::{*, dev::*};
+    ///
+    /// let builder = || {
+    ///     let (sck, _) = Mock::pin("SCK").without_log().init();
+    ///     let (mosi, _) = Mock::pin("MOSI").without_log().init();
+    ///     let (miso, _) = Mock::input_pin("MISO").without_log().init();
+    ///     Transport::bitbang(sck, mosi, miso, NoDelay).with_max_chunk(4)
+    /// };
+    ///
+    /// let mut spi = builder().init();
+    /// assert_eq!(spi.transfer(&mut [0x01; 5]).unwrap_err(), ErrorKind::TooLong);
+    ///
+    /// let (cs, _) = Mock::pin("CS").without_log().init();
+    /// let mut spi = builder().with_cs(cs).init();
+    /// assert_eq!(spi.max_transfer_len(), Some(4));
+    /// assert!(spi.transfer(&mut [0x01; 5]).is_ok());
+    /// # }
+    /// ```
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
+    /// Use the provided chip select pin.
+    pub fn with_cs<CS: OutputPin>(
+        self,
+        cs: CS,
+    ) -> BitbangChipSelectBuilder<SCK, MOSI, MISO, D, CS> {
+        BitbangChipSelectBuilder {
+            bus: self,
+            polarity: Polarity::IdleHigh,
+            cs,
+        }
+    }
+
+    /// Initialize the transport.
+    ///
+    /// Chip select is not handled.
+    pub fn init(self) -> auto::Transport<SCK, MOSI, MISO, D> {
+        let max_chunk = self.max_chunk;
+        auto::Transport::new(self.bus(), max_chunk)
+    }
+
+    fn bus(self) -> Bus<SCK, MOSI, MISO, D> {
+        Bus::new(
+            self.sck,
+            self.mosi,
+            self.miso,
+            self.delay,
+            self.mode,
+            self.bit_order,
+            self.half_period_us,
+        )
+    }
+}
+
+pub struct BitbangChipSelectBuilder<
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+    D: DelayUs<u32>,
+    CS: OutputPin,
+> {
+    bus: BitbangBuilder<SCK, MOSI, MISO, D>,
+    cs: CS,
+    polarity: Polarity,
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin>
+    BitbangChipSelectBuilder<SCK, MOSI, MISO, D, CS>
+{
+    /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Split transfers into chunks of at most `len` bytes, while the chip
+    /// stays selected.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.bus.max_chunk = Some(len);
+        self
+    }
+
+    /// Initialize the transport.
+    pub fn init(self) -> cs::Transport<SCK, MOSI, MISO, D, CS> {
+        let max_chunk = self.bus.max_chunk;
+        cs::Transport::new(self.bus.bus(), self.cs, self.polarity, max_chunk)
+    }
+}
@@ -0,0 +1,155 @@
+use super::super::{Error, ErrorKind, Result};
+use super::BitbangError;
+use crate::{BitOrder, InputPin, Mode, OutputPin, Polarity};
+use core::convert::Infallible;
+use embedded_hal::{blocking::delay::DelayUs, spi::Phase};
+
+/// Clocks bytes through GPIO pins.
+pub struct Bus<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO,
+    delay: D,
+    mode: Mode,
+    bit_order: BitOrder,
+    half_period_us: u32,
+}
+
+/// The source of bus errors, for any chip select pin error `CS`.
+type Source<SCK, MOSI, MISO, CS> = BitbangError<
+    <SCK as OutputPin>::Error,
+    <MOSI as OutputPin>::Error,
+    <MISO as InputPin>::Error,
+    CS,
+>;
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>> Bus<SCK, MOSI, MISO, D> {
+    pub fn new(
+        sck: SCK,
+        mosi: MOSI,
+        miso: MISO,
+        delay: D,
+        mode: Mode,
+        bit_order: BitOrder,
+        half_period_us: u32,
+    ) -> Self {
+        let mut bus = Self {
+            sck,
+            mosi,
+            miso,
+            delay,
+            mode,
+            bit_order,
+            half_period_us,
+        };
+
+        bus.set_sck::<Infallible>(false).ok();
+        bus
+    }
+
+    /// Exchange bytes in place. Errors tell how many bytes were exchanged.
+    pub fn exchange<CS>(&mut self, words: &mut [u8]) -> Result<(), Source<SCK, MOSI, MISO, CS>> {
+        for (index, word) in words.iter_mut().enumerate() {
+            *word = self
+                .exchange_byte::<CS>(*word)
+                .map_err(|err| err.with_completed(index))?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the clock speed by deriving the half period. Speeds above 500kHz
+    /// run as fast as the pins allow.
+    pub fn set_clock_speed<CS>(&mut self, speed: u32) -> Result<(), Source<SCK, MOSI, MISO, CS>> {
+        if speed == 0 {
+            return Err(ErrorKind::ClockSpeed.into());
+        }
+
+        self.half_period_us = 500_000 / speed;
+        Ok(())
+    }
+
+    /// Set the mode, returning the clock to its idle level.
+    pub fn set_spi_mode<CS>(&mut self, mode: Mode) -> Result<(), Source<SCK, MOSI, MISO, CS>> {
+        self.mode = mode;
+        self.set_sck::<CS>(false)
+            .map_err(|err| match err.into_device_error() {
+                Some(source) => Error::SpiMode.with_source(source),
+                None => Error::SpiMode,
+            })
+    }
+
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        self.bit_order = order;
+    }
+
+    pub fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+
+    fn exchange_byte<CS>(&mut self, tx: u8) -> Result<u8, Source<SCK, MOSI, MISO, CS>> {
+        let mut rx = 0;
+
+        for bit in 0..8 {
+            let shift = match self.bit_order {
+                BitOrder::MsbFirst => 7 - bit,
+                BitOrder::LsbFirst => bit,
+            };
+
+            let out = (tx >> shift) & 1 == 1;
+            let sample = match self.mode.phase {
+                Phase::CaptureOnFirstTransition => {
+                    self.set_mosi::<CS>(out)?;
+                    self.wait();
+                    self.set_sck::<CS>(true)?;
+                    let sample = self.read_miso::<CS>()?;
+                    self.wait();
+                    self.set_sck::<CS>(false)?;
+                    sample
+                }
+                Phase::CaptureOnSecondTransition => {
+                    self.set_sck::<CS>(true)?;
+                    self.set_mosi::<CS>(out)?;
+                    self.wait();
+                    self.set_sck::<CS>(false)?;
+                    let sample = self.read_miso::<CS>()?;
+                    self.wait();
+                    sample
+                }
+            };
+
+            rx |= u8::from(sample) << shift;
+        }
+
+        Ok(rx)
+    }
+
+    /// Drive the clock to its active level, or back to idle.
+    fn set_sck<CS>(&mut self, active: bool) -> Result<(), Source<SCK, MOSI, MISO, CS>> {
+        match (self.mode.polarity == Polarity::IdleHigh) != active {
+            true => self.sck.set_high(),
+            false => self.sck.set_low(),
+        }
+        .map_err(|err| Error::Transfer.with_source(BitbangError::Sck(err)))
+    }
+
+    fn set_mosi<CS>(&mut self, high: bool) -> Result<(), Source<SCK, MOSI, MISO, CS>> {
+        match high {
+            true => self.mosi.set_high(),
+            false => self.mosi.set_low(),
+        }
+        .map_err(|err| Error::Transfer.with_source(BitbangError::Mosi(err)))
+    }
+
+    fn read_miso<CS>(&mut self) -> Result<bool, Source<SCK, MOSI, MISO, CS>> {
+        self.miso
+            .is_high()
+            .map_err(|err| Error::Transfer.with_source(BitbangError::Miso(err)))
+    }
+
+    fn wait(&mut self) {
+        if self.half_period_us > 0 {
+            self.delay.delay_us(self.half_period_us);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use super::super::{chunk::chunked, Error, Result};
+use super::{bus::Bus, BitbangError};
+use crate::{
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, InputPin, Mode, OutputPin, Polarity, SpiDev,
+    SpiModeControl, Transfer,
+};
+use embedded_hal::blocking::delay::DelayUs;
+
+#[cfg(feature = "eh1")]
+use core::fmt::Debug;
+
+pub struct Transport<
+    SCK: OutputPin,
+    MOSI: OutputPin,
+    MISO: InputPin,
+    D: DelayUs<u32>,
+    CS: OutputPin,
+> {
+    bus: Bus<SCK, MOSI, MISO, D>,
+    cs: CS,
+    polarity: Polarity,
+    max_chunk: Option<usize>,
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin>
+    Transport<SCK, MOSI, MISO, D, CS>
+{
+    pub fn new(
+        bus: Bus<SCK, MOSI, MISO, D>,
+        cs: CS,
+        polarity: Polarity,
+        max_chunk: Option<usize>,
+    ) -> Self {
+        let mut transport = Self {
+            bus,
+            cs,
+            polarity,
+            max_chunk,
+        };
+
+        transport.deselect().ok();
+        transport
+    }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin> Transfer<u8>
+    for Transport<SCK, MOSI, MISO, D, CS>
+{
+    impl_cs_transfer_common!();
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin> SpiDev
+    for Transport<SCK, MOSI, MISO, D, CS>
+{
+    type Source = BitbangError<SCK::Error, MOSI::Error, MISO::Error, CS::Error>;
+
+    impl_cs_common!(BitbangError::Cs);
+    impl_bitbang_common!();
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
+
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Source> {
+        let bus = &mut self.bus;
+        chunked(words, self.max_chunk, |chunk| bus.exchange(chunk))
+    }
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin> ChipSelect
+    for Transport<SCK, MOSI, MISO, D, CS>
+{
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin> ClockSpeed
+    for Transport<SCK, MOSI, MISO, D, CS>
+{
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin> SpiModeControl
+    for Transport<SCK, MOSI, MISO, D, CS>
+{
+}
+
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin>
+    BitOrderControl for Transport<SCK, MOSI, MISO, D, CS>
+{
+}
+
+#[cfg(feature = "eh1")]
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin>
+    _eh1::spi::ErrorType for Transport<SCK, MOSI, MISO, D, CS>
+where
+    SCK::Error: Debug,
+    MOSI::Error: Debug,
+    MISO::Error: Debug,
+    CS::Error: Debug,
+{
+    type Error = Error<<Self as SpiDev>::Source>;
+}
+
+#[cfg(feature = "eh1")]
+impl<SCK: OutputPin, MOSI: OutputPin, MISO: InputPin, D: DelayUs<u32>, CS: OutputPin>
+    _eh1::spi::SpiDevice for Transport<SCK, MOSI, MISO, D, CS>
+where
+    SCK::Error: Debug,
+    MOSI::Error: Debug,
+    MISO::Error: Debug,
+    CS::Error: Debug,
+{
+    impl_eh1_device_common!();
+}
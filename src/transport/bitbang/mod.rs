@@ -0,0 +1,49 @@
+mod auto;
+mod build;
+mod bus;
+mod cs;
+
+use core::{convert::Infallible, fmt};
+
+/// The source of an [`Error`](super::Error) from a bit-banged transport:
+/// the error of the pin which failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitbangError<SCK, MOSI, MISO, CS = Infallible> {
+    /// Driving the clock failed.
+    Sck(SCK),
+    /// Driving MOSI failed.
+    Mosi(MOSI),
+    /// Reading MISO failed.
+    Miso(MISO),
+    /// The chip select pin failed.
+    Cs(CS),
+}
+
+impl<SCK, MOSI, MISO, CS> fmt::Display for BitbangError<SCK, MOSI, MISO, CS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BitbangError::Sck(_) => write!(f, "SCK pin error"),
+            BitbangError::Mosi(_) => write!(f, "MOSI pin error"),
+            BitbangError::Miso(_) => write!(f, "MISO pin error"),
+            BitbangError::Cs(_) => write!(f, "Chip select pin error"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<SCK, MOSI, MISO, CS> std::error::Error for BitbangError<SCK, MOSI, MISO, CS>
+where
+    SCK: std::error::Error + 'static,
+    MOSI: std::error::Error + 'static,
+    MISO: std::error::Error + 'static,
+    CS: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BitbangError::Sck(err) => Some(err),
+            BitbangError::Mosi(err) => Some(err),
+            BitbangError::Miso(err) => Some(err),
+            BitbangError::Cs(err) => Some(err),
+        }
+    }
+}
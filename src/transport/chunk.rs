@@ -0,0 +1,32 @@
+use super::{ErrorKind, Result};
+
+/// Fail with [`TooLong`](ErrorKind::TooLong) if more than `max` bytes are
+/// given to a transport which cannot split them.
+pub(crate) fn check_len<E>(len: usize, max: Option<usize>) -> Result<(), E> {
+    match max {
+        Some(max) if max > 0 && len > max => Err(ErrorKind::TooLong.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Exchange the bytes through `exchange` in chunks of at most `max` bytes,
+/// counting the bytes of earlier chunks in errors.
+pub(crate) fn chunked<E, F>(
+    words: &mut [u8],
+    max: Option<usize>,
+    mut exchange: F,
+) -> Result<&[u8], E>
+where
+    F: FnMut(&mut [u8]) -> Result<(), E>,
+{
+    match max {
+        Some(max) if max > 0 && words.len() > max => {
+            for (index, chunk) in words.chunks_mut(max).enumerate() {
+                exchange(chunk).map_err(|err| err.offset(index * max))?;
+            }
+        }
+        _ => exchange(words)?,
+    }
+
+    Ok(words)
+}
@@ -1,35 +1,97 @@
 #[macro_export]
 macro_rules! impl_cs_common {
-    () => {
+    (timed, $pin:expr) => {
+        fn is_chip_select(&self) -> bool {
+            true
+        }
+
+        fn select(&mut self) -> Result<(), Self::Source> {
+            let (cs, polarity) = (&mut self.cs, self.polarity);
+
+            self.timer.select(|| {
+                match polarity {
+                    Polarity::IdleHigh => cs.set_low(),
+                    Polarity::IdleLow => cs.set_high(),
+                }
+                .map_err(|err| $crate::transport::Error::ChipSelect.with_source($pin(err)))
+            })
+        }
+
+        fn deselect(&mut self) -> Result<(), Self::Source> {
+            let (cs, polarity) = (&mut self.cs, self.polarity);
+
+            self.timer.deselect(|| {
+                match polarity {
+                    Polarity::IdleHigh => cs.set_high(),
+                    Polarity::IdleLow => cs.set_low(),
+                }
+                .map_err(|err| $crate::transport::Error::ChipDeselect.with_source($pin(err)))
+            })
+        }
+
+        fn delay_us(&mut self, us: u32) -> Result<(), Self::Source> {
+            self.timer.delay_us(us)
+        }
+    };
+    (async, $pin:expr) => {
+        fn is_chip_select(&self) -> bool {
+            true
+        }
+
+        async fn select(&mut self) -> Result<(), Self::Source> {
+            match self.polarity {
+                Polarity::IdleHigh => self.cs.set_low(),
+                Polarity::IdleLow => self.cs.set_high(),
+            }
+            .map_err(|err| $crate::transport::Error::ChipSelect.with_source($pin(err)))
+        }
+
+        async fn deselect(&mut self) -> Result<(), Self::Source> {
+            match self.polarity {
+                Polarity::IdleHigh => self.cs.set_high(),
+                Polarity::IdleLow => self.cs.set_low(),
+            }
+            .map_err(|err| $crate::transport::Error::ChipDeselect.with_source($pin(err)))
+        }
+    };
+    ($pin:expr) => {
         fn is_chip_select(&self) -> bool {
             true
         }
 
-        fn select(&mut self) -> Result {
+        fn select(&mut self) -> Result<(), Self::Source> {
             match self.polarity {
                 Polarity::IdleHigh => self.cs.set_low(),
                 Polarity::IdleLow => self.cs.set_high(),
             }
-            .or(Err(Error::ChipSelect))
+            .map_err(|err| $crate::transport::Error::ChipSelect.with_source($pin(err)))
         }
 
-        fn deselect(&mut self) -> Result {
+        fn deselect(&mut self) -> Result<(), Self::Source> {
             match self.polarity {
                 Polarity::IdleHigh => self.cs.set_high(),
                 Polarity::IdleLow => self.cs.set_low(),
             }
-            .or(Err(Error::ChipDeselect))
+            .map_err(|err| $crate::transport::Error::ChipDeselect.with_source($pin(err)))
         }
     };
 }
 
 #[macro_export]
 macro_rules! impl_auto_transfer_common {
-    () => {
-        type Error = Error;
+    ($source:ty, $spi:expr) => {
+        type Error = Error<$source>;
 
-        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-            self.spi.transfer(words).or(Err(Error::Transfer))
+        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], $source> {
+            let max = $crate::SpiDev::max_transfer_len(self);
+            $crate::transport::chunk::check_len(words.len(), max)?;
+
+            $crate::transport::bit_order::transfer_with(
+                &mut self.spi,
+                self.bit_order,
+                words,
+                |err| $crate::transport::Error::Transfer.with_source($spi(err)),
+            )
         }
     };
 }
@@ -37,12 +99,77 @@ macro_rules! impl_auto_transfer_common {
 #[macro_export]
 macro_rules! impl_cs_transfer_common {
     () => {
-        type Error = Error;
+        type Error = Error<<Self as SpiDev>::Source>;
 
-        fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
+        fn transfer<'w>(
+            &mut self,
+            words: &'w mut [u8],
+        ) -> Result<&'w [u8], <Self as SpiDev>::Source> {
             self.select()
                 .and_then(|_| self.raw_transfer_or_deselect(words))
                 .and_then(|res| self.deselect().and(Ok(res)))
         }
     };
 }
+
+#[macro_export]
+macro_rules! impl_soft_bit_order_common {
+    () => {
+        fn is_bit_order(&self) -> bool {
+            true
+        }
+
+        fn set_bit_order(&mut self, order: BitOrder) -> Result<(), Self::Source> {
+            self.bit_order = order;
+            Ok(())
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! impl_bitbang_common {
+    () => {
+        fn is_clock_speed(&self) -> bool {
+            true
+        }
+
+        fn is_spi_mode(&self) -> bool {
+            true
+        }
+
+        fn is_bit_order(&self) -> bool {
+            true
+        }
+
+        fn set_clock_speed(&mut self, speed: u32) -> Result<(), Self::Source> {
+            self.bus.set_clock_speed(speed)
+        }
+
+        fn set_spi_mode(&mut self, mode: Mode) -> Result<(), Self::Source> {
+            self.bus.set_spi_mode(mode)
+        }
+
+        fn set_bit_order(&mut self, order: BitOrder) -> Result<(), Self::Source> {
+            self.bus.set_bit_order(order);
+            Ok(())
+        }
+
+        fn delay_us(&mut self, us: u32) -> Result<(), Self::Source> {
+            self.bus.delay_us(us);
+            Ok(())
+        }
+    };
+}
+
+#[cfg(feature = "eh1")]
+#[macro_export]
+macro_rules! impl_eh1_device_common {
+    () => {
+        fn transaction(
+            &mut self,
+            operations: &mut [_eh1::spi::Operation<'_, u8>],
+        ) -> Result<(), <Self as SpiDev>::Source> {
+            $crate::transport::eh1::transaction(self, operations)
+        }
+    };
+}
@@ -0,0 +1,175 @@
+use super::{transaction, Error, ErrorKind as Phase, Operation, Result, SpiDev};
+use _eh1::{
+    digital,
+    spi::{self, ErrorKind, Operation as SpiOperation, SpiBus},
+};
+
+/// Adapts an [`embedded_hal`] 1.0 bus or pin for use where the 0.2 traits
+/// are expected, such as [`Transport::hal`](crate::Transport::hal).
+///
+/// - An [`SpiBus<u8>`](SpiBus) implements [`Transfer<u8>`](crate::Transfer).
+/// - A [1.0 `OutputPin`](digital::OutputPin) implements
+///   [`OutputPin`](crate::OutputPin).
+///
+/// The 1.0 traits of the wrapped value remain available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Eh1<T>(pub T);
+
+impl<T> Eh1<T> {
+    /// Unwrap the bus or pin.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: SpiBus<u8>> crate::Transfer<u8> for Eh1<T> {
+    type Error = T::Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> core::result::Result<&'w [u8], T::Error> {
+        self.0.transfer_in_place(words)?;
+        self.0.flush()?;
+        Ok(words)
+    }
+}
+
+impl<T: digital::OutputPin> crate::OutputPin for Eh1<T> {
+    type Error = T::Error;
+
+    fn set_low(&mut self) -> core::result::Result<(), T::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> core::result::Result<(), T::Error> {
+        self.0.set_high()
+    }
+}
+
+impl<T: spi::ErrorType> spi::ErrorType for Eh1<T> {
+    type Error = T::Error;
+}
+
+impl<T: SpiBus<u8>> SpiBus<u8> for Eh1<T> {
+    fn read(&mut self, words: &mut [u8]) -> core::result::Result<(), T::Error> {
+        self.0.read(words)
+    }
+
+    fn write(&mut self, words: &[u8]) -> core::result::Result<(), T::Error> {
+        self.0.write(words)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> core::result::Result<(), T::Error> {
+        self.0.transfer(read, write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> core::result::Result<(), T::Error> {
+        self.0.transfer_in_place(words)
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), T::Error> {
+        self.0.flush()
+    }
+}
+
+impl<T: digital::ErrorType> digital::ErrorType for Eh1<T> {
+    type Error = T::Error;
+}
+
+impl<T: digital::OutputPin> digital::OutputPin for Eh1<T> {
+    fn set_low(&mut self) -> core::result::Result<(), T::Error> {
+        self.0.set_low()
+    }
+
+    fn set_high(&mut self) -> core::result::Result<(), T::Error> {
+        self.0.set_high()
+    }
+}
+
+impl<E: core::fmt::Debug> spi::Error for Error<E> {
+    fn kind(&self) -> ErrorKind {
+        if self.deselect_failed() {
+            return ErrorKind::ChipSelectFault;
+        }
+
+        match self.kind() {
+            Phase::ChipSelect | Phase::ChipDeselect => ErrorKind::ChipSelectFault,
+            Phase::Transfer
+            | Phase::ClockSpeed
+            | Phase::SpiMode
+            | Phase::BitOrder
+            | Phase::WordSize
+            | Phase::BusBusy
+            | Phase::TooLong
+            | Phase::NotImplemented => ErrorKind::Other,
+        }
+    }
+}
+
+/// Run 1.0 operations on an [`SpiDev`] with the same chip select behaviour
+/// as [`SpiDev::transaction`]. The chip must stay selected throughout, so
+/// transports which do not control chip selection return
+/// [`NotImplemented`](Phase::NotImplemented).
+pub(crate) fn transaction<S: SpiDev + ?Sized>(
+    spi: &mut S,
+    operations: &mut [SpiOperation<'_, u8>],
+) -> Result<(), S::Source> {
+    if !spi.is_chip_select() {
+        return Err(Phase::NotImplemented.into());
+    }
+
+    let exchange: fn(&mut S, &mut [u8]) -> Result<(), S::Source> =
+        |spi, words| spi.raw_transfer(words).and(Ok(()));
+
+    spi.select()?;
+
+    let mut done = 0;
+
+    for operation in operations.iter_mut() {
+        let len = match operation {
+            SpiOperation::Read(words) => words.len(),
+            SpiOperation::Write(words) => words.len(),
+            SpiOperation::TransferInPlace(words) => words.len(),
+            SpiOperation::Transfer(read, write) => read.len().max(write.len()),
+            SpiOperation::DelayNs(_) => 0,
+        };
+
+        let result = match operation {
+            SpiOperation::Read(words) => Operation::Read(words).run(spi, exchange),
+            SpiOperation::Write(words) => Operation::Write(words).run(spi, exchange),
+            SpiOperation::TransferInPlace(words) => Operation::Transfer(words).run(spi, exchange),
+            SpiOperation::Transfer(read, write) => transfer_split(spi, read, write, exchange),
+            SpiOperation::DelayNs(ns) => spi.delay_us(ns.div_ceil(1000)),
+        };
+
+        if let Err(err) = result {
+            return Err(err.offset(done).after_deselect(spi.deselect()));
+        }
+
+        done += len;
+    }
+
+    spi.deselect()
+}
+
+/// Exchange `write` while reading into `read`. The longer buffer determines
+/// the length: missing write bytes are `0x00` and extra read bytes are
+/// discarded.
+fn transfer_split<S: SpiDev + ?Sized>(
+    spi: &mut S,
+    read: &mut [u8],
+    write: &[u8],
+    exchange: fn(&mut S, &mut [u8]) -> Result<(), S::Source>,
+) -> Result<(), S::Source> {
+    let len = read.len().max(write.len());
+
+    transaction::copied(
+        write,
+        len,
+        true,
+        |words| exchange(spi, words),
+        |start, words| {
+            let rx = read.get_mut(start..).unwrap_or_default();
+            let rx_len = rx.len().min(words.len());
+            rx[..rx_len].copy_from_slice(&words[..rx_len]);
+        },
+    )
+}
@@ -1,33 +1,257 @@
-/// Indicates an SPI error. The [`Transport`](super::traits::Transport)
-/// in use determines which errors are possible.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Error {
+use core::{convert::Infallible, fmt};
+
+/// The phase of an SPI operation which failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
     Transfer,
     ChipSelect,
     ChipDeselect,
     ClockSpeed,
+    SpiMode,
+    BitOrder,
+    WordSize,
+    /// Another device on a [`SharedBus`](crate::SharedBus) has its chip
+    /// selected.
+    BusBusy,
+    /// A transfer is longer than the
+    /// [`max_transfer_len`](crate::SpiDev::max_transfer_len) of a transport
+    /// which cannot split it.
+    TooLong,
     NotImplemented,
 }
 
-/// Result where the Err is an SPI [`Error`].
-pub type Result<T = ()> = core::result::Result<T, Error>;
-
-#[cfg(feature = "std")]
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
             "{}",
             match self {
-                Error::Transfer => "SPI transfer error",
-                Error::ChipSelect => "Select SPI chip error",
-                Error::ChipDeselect => "Deselect SPI chip error",
-                Error::ClockSpeed => "Set SPI clock speed error",
-                Error::NotImplemented => "That feature is not implemented",
+                ErrorKind::Transfer => "SPI transfer error",
+                ErrorKind::ChipSelect => "Select SPI chip error",
+                ErrorKind::ChipDeselect => "Deselect SPI chip error",
+                ErrorKind::ClockSpeed => "Set SPI clock speed error",
+                ErrorKind::SpiMode => "Set SPI mode error",
+                ErrorKind::BitOrder => "Set SPI bit order error",
+                ErrorKind::WordSize => "Set SPI word size error",
+                ErrorKind::BusBusy => "SPI bus held by another device",
+                ErrorKind::TooLong => "SPI transfer too long for the device",
+                ErrorKind::NotImplemented => "That feature is not implemented",
             }
         )
     }
 }
 
+/// Indicates an SPI error. The [`Transport`](super::traits::Transport)
+/// in use determines which errors are possible.
+///
+/// Besides the [phase](ErrorKind) which failed, an error may tell how many
+/// bytes were exchanged before the failure, and whether deselecting the chip
+/// after the failure failed too. The error of the underlying bus or pin is
+/// kept as the source `E`, which is the [`Source`](super::SpiDev::Source)
+/// of the transport. With the `std` feature, it is also returned by
+/// [`source`](std::error::Error::source).
+///
+/// # Migrating from the `Error` enum
+///
+/// `Error` used to be an enum of phases. Each former variant is now an
+/// associated constant without a source or progress, so
+/// `Err(Error::Transfer)` still constructs an error, and compares equal to
+/// an error from a transport only if that error has neither. Match on
+/// [`kind`](Self::kind) to handle a phase regardless of the rest:
+///
+/// ```
+/// use rpio_utils::{Error, ErrorKind};
+///
+/// let err: Error = Error::Transfer.with_completed(3);
+///
+/// assert_ne!(err, Error::Transfer);
+/// assert_eq!(err, ErrorKind::Transfer);
+/// assert!(matches!(err.kind(), ErrorKind::Transfer));
+/// ```
+///
+/// An error is [`Copy`] when its source is, as it is for the default source
+/// [`Infallible`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error<E = Infallible> {
+    kind: ErrorKind,
+    completed: Option<usize>,
+    deselect_failed: bool,
+    source: Option<E>,
+}
+
+#[allow(non_upper_case_globals)]
+impl<E> Error<E> {
+    pub const Transfer: Self = Self::new(ErrorKind::Transfer);
+    pub const ChipSelect: Self = Self::new(ErrorKind::ChipSelect);
+    pub const ChipDeselect: Self = Self::new(ErrorKind::ChipDeselect);
+    pub const ClockSpeed: Self = Self::new(ErrorKind::ClockSpeed);
+    pub const SpiMode: Self = Self::new(ErrorKind::SpiMode);
+    pub const BitOrder: Self = Self::new(ErrorKind::BitOrder);
+    pub const WordSize: Self = Self::new(ErrorKind::WordSize);
+    pub const BusBusy: Self = Self::new(ErrorKind::BusBusy);
+    pub const TooLong: Self = Self::new(ErrorKind::TooLong);
+    pub const NotImplemented: Self = Self::new(ErrorKind::NotImplemented);
+}
+
+impl<E> Error<E> {
+    pub const fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            completed: None,
+            deselect_failed: false,
+            source: None,
+        }
+    }
+
+    /// The phase which failed.
+    pub const fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The number of bytes exchanged before the failure, if known.
+    pub const fn completed(&self) -> Option<usize> {
+        self.completed
+    }
+
+    /// Whether deselecting the chip after the failure failed too, leaving
+    /// it selected.
+    pub const fn deselect_failed(&self) -> bool {
+        self.deselect_failed
+    }
+
+    /// The error of the underlying bus or pin, if any.
+    pub const fn device_error(&self) -> Option<&E> {
+        self.source.as_ref()
+    }
+
+    /// Take the error of the underlying bus or pin, if any.
+    pub fn into_device_error(self) -> Option<E> {
+        self.source
+    }
+
+    /// Record the number of bytes exchanged before the failure.
+    pub const fn with_completed(mut self, bytes: usize) -> Self {
+        self.completed = Some(bytes);
+        self
+    }
+
+    /// Record that deselecting the chip after the failure failed too.
+    pub const fn with_deselect_failed(mut self) -> Self {
+        self.deselect_failed = true;
+        self
+    }
+
+    /// Keep the error of the underlying bus or pin.
+    pub fn with_source(self, source: E) -> Self {
+        Self {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    /// Convert the source, keeping the phase and progress.
+    pub fn map_source<F>(self, f: impl FnOnce(E) -> F) -> Error<F> {
+        Error {
+            kind: self.kind,
+            completed: self.completed,
+            deselect_failed: self.deselect_failed,
+            source: self.source.map(f),
+        }
+    }
+
+    /// Count the bytes exchanged before the step which failed. When the
+    /// progress of that step is unknown, these are the bytes known to be
+    /// exchanged.
+    pub(crate) fn offset(mut self, bytes: usize) -> Self {
+        self.completed = Some(self.completed.unwrap_or(0) + bytes);
+        self
+    }
+
+    /// Record the outcome of deselecting the chip after the failure.
+    pub(crate) fn after_deselect<T>(self, deselect: core::result::Result<T, Self>) -> Self {
+        match deselect {
+            Ok(_) => self,
+            Err(_) => self.with_deselect_failed(),
+        }
+    }
+}
+
+impl Error {
+    /// Use the error where another source is expected. It has no source.
+    pub fn widen<F>(self) -> Error<F> {
+        self.map_source(|never| match never {})
+    }
+}
+
+impl<E> From<ErrorKind> for Error<E> {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl<E> PartialEq<ErrorKind> for Error<E> {
+    fn eq(&self, kind: &ErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+/// Result where the Err is an SPI [`Error`].
+pub type Result<T = (), E = Infallible> = core::result::Result<T, Error<E>>;
+
+impl<E> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if let Some(completed) = self.completed {
+            write!(f, " after {} bytes", completed)?;
+        }
+
+        if self.deselect_failed {
+            write!(f, ", and deselecting the chip failed")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// The source of an [`Error`] from a transport which drives both a bus and
+/// a chip select pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceError<S, P> {
+    /// The bus failed.
+    Spi(S),
+    /// The chip select pin failed.
+    Pin(P),
+}
+
+impl<S, P> fmt::Display for DeviceError<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::Spi(_) => write!(f, "SPI bus error"),
+            DeviceError::Pin(_) => write!(f, "Chip select pin error"),
+        }
+    }
+}
+
 #[cfg(feature = "std")]
-impl std::error::Error for Error {}
+impl<S, P> std::error::Error for DeviceError<S, P>
+where
+    S: std::error::Error + 'static,
+    P: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeviceError::Spi(err) => Some(err),
+            DeviceError::Pin(err) => Some(err),
+        }
+    }
+}
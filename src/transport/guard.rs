@@ -0,0 +1,120 @@
+use super::{ChipSelect, Operation, Result};
+use core::ops::{Deref, DerefMut};
+
+/// Keeps a chip selected until it is dropped or [finished](Self::finish).
+///
+/// Returned by [`ChipSelect::selected`]. Dereferences to [`Selected`], which
+/// only exchanges bytes, so the chip cannot be deselected or reconfigured
+/// while the guard is alive. Dropping the guard deselects the chip and
+/// ignores any error; use [`finish`](Self::finish) to observe it.
+///
+/// ```
+/// # #[cfg(feature = "dev")] {
+/// use rpio_utils::{*, dev::{*, spi::mock::SpiError}};
+///
+/// fn read<S: ChipSelect>(spi: &mut S, buffer: &mut [u8]) -> Result<(), Error<S::Source>> {
+///     let mut guard = spi.selected()?;
+///     guard.write(&[0x03, 0x00])?; // an early return still deselects
+///     guard.read(buffer)?;
+///     guard.finish()
+/// }
+///
+/// let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+/// let (cs, cs_control) = Mock::pin("MockCS").without_log().init();
+/// let mut spi = Transport::hal(spi).with_cs(cs).init();
+/// let mut buffer = [0; 4];
+///
+/// spi_control.set_error(SpiError::Transfer);
+/// assert_eq!(read(&mut spi, &mut buffer).unwrap_err(), ErrorKind::Transfer);
+/// assert!(cs_control.get_value());
+///
+/// // Only finish reports that the chip could not be deselected
+/// spi_control.clear_error();
+/// cs_control.set_error(PinError::SetHigh);
+/// let err = read(&mut spi, &mut buffer).unwrap_err();
+/// assert_eq!(err, ErrorKind::ChipDeselect);
+/// assert_eq!(err.device_error(), Some(&DeviceError::Pin(PinError::SetHigh)));
+///
+/// // Dropping the guard ignores it
+/// drop(spi.selected().unwrap());
+/// # }
+/// ```
+pub struct SelectGuard<'a, S: ChipSelect + ?Sized> {
+    selected: Selected<'a, S>,
+    active: bool,
+}
+
+impl<'a, S: ChipSelect + ?Sized> SelectGuard<'a, S> {
+    pub(crate) fn new(spi: &'a mut S) -> Result<Self, S::Source> {
+        spi.select()?;
+
+        Ok(Self {
+            selected: Selected { spi },
+            active: true,
+        })
+    }
+
+    /// Deselect the chip, returning any error.
+    pub fn finish(mut self) -> Result<(), S::Source> {
+        self.active = false;
+        self.selected.spi.deselect()
+    }
+}
+
+impl<'a, S: ChipSelect + ?Sized> Deref for SelectGuard<'a, S> {
+    type Target = Selected<'a, S>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.selected
+    }
+}
+
+impl<S: ChipSelect + ?Sized> DerefMut for SelectGuard<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.selected
+    }
+}
+
+impl<S: ChipSelect + ?Sized> Drop for SelectGuard<'_, S> {
+    fn drop(&mut self) {
+        if self.active {
+            self.selected.spi.deselect().ok();
+        }
+    }
+}
+
+/// The operations available while a [`SelectGuard`] holds the chip
+/// selected.
+pub struct Selected<'a, S: ChipSelect + ?Sized> {
+    spi: &'a mut S,
+}
+
+impl<S: ChipSelect + ?Sized> Selected<'_, S> {
+    /// Exchange bytes in place. See [`SpiDev::raw_transfer`](super::SpiDev::raw_transfer).
+    pub fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], S::Source> {
+        self.spi.raw_transfer(words)
+    }
+
+    /// Write the bytes, discarding the bytes received.
+    pub fn write(&mut self, words: &[u8]) -> Result<(), S::Source> {
+        self.operations(&mut [Operation::Write(words)])
+    }
+
+    /// Read into the buffer, writing `0x00` bytes.
+    pub fn read(&mut self, words: &mut [u8]) -> Result<(), S::Source> {
+        self.operations(&mut [Operation::Read(words)])
+    }
+
+    /// Wait for the given number of microseconds.
+    pub fn delay_us(&mut self, us: u32) -> Result<(), S::Source> {
+        self.spi.delay_us(us)
+    }
+
+    /// Run the operations in order, stopping at the first error. The chip
+    /// stays selected throughout.
+    pub fn operations(&mut self, operations: &mut [Operation<'_>]) -> Result<(), S::Source> {
+        operations.iter_mut().try_for_each(|operation| {
+            operation.run(self.spi, |spi, words| spi.raw_transfer(words).and(Ok(())))
+        })
+    }
+}
@@ -1,18 +1,45 @@
-use super::super::{Error, Result};
-use crate::{SpiDev, Transfer};
+use super::super::{transaction, Error, Result};
+use crate::{BitOrder, BitOrderControl, Operation, SpiDev, Transfer};
 
 pub struct Transport<SPI: Transfer<u8>> {
     spi: SPI,
+    bit_order: BitOrder,
+    max_chunk: Option<usize>,
 }
 
 impl<SPI: Transfer<u8>> Transport<SPI> {
-    pub fn new(spi: SPI) -> Self {
-        Self { spi }
+    pub fn new(spi: SPI, max_chunk: Option<usize>) -> Self {
+        Self {
+            spi,
+            bit_order: BitOrder::MsbFirst,
+            max_chunk,
+        }
     }
 }
 
 impl<SPI: Transfer<u8>> Transfer<u8> for Transport<SPI> {
-    impl_auto_transfer_common!();
+    impl_auto_transfer_common!(SPI::Error, |err| err);
 }
 
-impl<SPI: Transfer<u8>> SpiDev for Transport<SPI> {}
+impl<SPI: Transfer<u8>> SpiDev for Transport<SPI> {
+    type Source = SPI::Error;
+
+    impl_soft_bit_order_common!();
+
+    /// Transfers are not split, since the device selects the chip for each,
+    /// so longer transfers fail with [`TooLong`](crate::ErrorKind::TooLong).
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
+
+    /// Runs the operations as a single transfer, so that the chip remains
+    /// selected throughout. Delays would deselect it, so they return
+    /// [`NotImplemented`](crate::ErrorKind::NotImplemented).
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), SPI::Error> {
+        transaction::grouped(operations, |words| {
+            Transfer::transfer(self, words).and(Ok(()))
+        })
+    }
+}
+
+impl<SPI: Transfer<u8>> BitOrderControl for Transport<SPI> {}
@@ -1,15 +1,20 @@
-use super::{auto, cs};
-use crate::{OutputPin, Polarity, Transfer, Transport};
+use super::{super::timing::Timer, auto, cs};
+use crate::{NoDelay, OutputPin, Polarity, Timing, Transfer, Transport};
+use embedded_hal::blocking::delay::DelayUs;
 
 impl Transport {
     /// Construct a transport from any [`Transfer<u8>`](Transfer).
     pub fn hal<SPI: Transfer<u8>>(spi: SPI) -> HalBuilder<SPI> {
-        HalBuilder { spi }
+        HalBuilder {
+            spi,
+            max_chunk: None,
+        }
     }
 }
 
 pub struct HalBuilder<SPI: Transfer<u8>> {
     spi: SPI,
+    max_chunk: Option<usize>,
 }
 
 impl<SPI: Transfer<u8>> HalBuilder<SPI> {
@@ -19,32 +24,148 @@ impl<SPI: Transfer<u8>> HalBuilder<SPI> {
             spi: self.spi,
             polarity: Polarity::IdleHigh,
             cs,
+            delay: None,
+            timing: Timing::default(),
+            max_chunk: self.max_chunk,
+            progress: |_| None,
         }
     }
 
+    /// Advertise the most bytes the device exchanges at once. Since the
+    /// device selects the chip for each transfer, longer transfers fail with
+    /// [`TooLong`](crate::ErrorKind::TooLong) rather than being split.
+    ///
+    /// ```
+    /// # #[cfg(feature = "dev")] {
+    /// use rpio_utils::{*, dev::*};
+    ///
+    /// let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+    /// let mut spi = Transport::hal(spi).with_max_chunk(4).init();
+    ///
+    /// assert_eq!(spi.max_transfer_len(), Some(4));
+    /// assert!(spi.transfer(&mut [0x01; 4]).is_ok());
+    /// assert_eq!(spi.transfer(&mut [0x01; 5]).unwrap_err(), ErrorKind::TooLong);
+    /// assert_eq!(spi_control.get_transfers().len(), 1);
+    /// # }
+    /// ```
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
     /// Initialize the transport.
     ///
-    /// Chip select must be handled by the provided SPI device.
+    /// Chip select must be handled by the provided SPI device, so a
+    /// [transaction](crate::SpiDev::transaction) runs as a single transfer:
+    ///
+    /// ```
+    /// # #[cfg(feature = "dev")] {
+    /// use rpio_utils::{*, dev::*};
+    ///
+    /// let (spi, spi_control) = Mock::spi("MockSPI")
+    ///     .without_log()
+    ///     .with_generator(|tx: &[u8]| tx.iter().map(|word| !word).collect())
+    ///     .init();
+    /// let mut spi = Transport::hal(spi).init();
+    ///
+    /// let mut data = [0; 2];
+    /// spi.transaction(&mut [Operation::Write(&[0x03, 0x10]), Operation::Read(&mut data)])
+    ///     .unwrap();
+    /// assert_eq!(data, [0xff, 0xff]);
+    /// assert_eq!(spi_control.get_traffic()[0].0, [0x03, 0x10, 0x00, 0x00]);
+    /// assert_eq!(spi_control.get_transfers().len(), 1);
+    ///
+    /// // Delays would deselect the chip
+    /// let err = spi.transaction(&mut [Operation::DelayUs(10)]).unwrap_err();
+    /// assert_eq!(err, ErrorKind::NotImplemented);
+    /// # }
+    /// ```
     pub fn init(self) -> auto::Transport<SPI> {
-        auto::Transport::new(self.spi)
+        auto::Transport::new(self.spi, self.max_chunk)
     }
 }
 
-pub struct HalChipSelectBuilder<SPI: Transfer<u8>, CS: OutputPin> {
+pub struct HalChipSelectBuilder<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32> = NoDelay> {
     spi: SPI,
     cs: CS,
     polarity: Polarity,
+    delay: Option<D>,
+    timing: Timing,
+    max_chunk: Option<usize>,
+    progress: fn(&SPI::Error) -> Option<usize>,
 }
 
-impl<SPI: Transfer<u8>, CS: OutputPin> HalChipSelectBuilder<SPI, CS> {
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> HalChipSelectBuilder<SPI, CS, D> {
     /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
     pub fn with_polarity(mut self, polarity: Polarity) -> Self {
         self.polarity = polarity;
         self
     }
 
+    /// Use the provided delay to satisfy the chip select timing on every
+    /// select and deselect. The delay also serves
+    /// [`Operation::DelayUs`](crate::Operation::DelayUs) and the `DelayNs`
+    /// operations of embedded-hal 1.0 transactions, which fail with
+    /// [`NotImplemented`](crate::ErrorKind::NotImplemented) without it:
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "dev", feature = "eh1"))] {
+    /// use _eh1::spi::{Operation as Eh1Operation, SpiDevice};
+    /// use rpio_utils::{*, dev::*};
+    ///
+    /// let mut operations = [Eh1Operation::Write(&[0x01]), Eh1Operation::DelayNs(10_000)];
+    ///
+    /// let (spi, _) = Mock::spi("MockSPI").without_log().init();
+    /// let (cs, _) = Mock::pin("MockCS").without_log().init();
+    /// let mut device = Transport::hal(spi).with_cs(cs).init();
+    /// let err = SpiDevice::transaction(&mut device, &mut operations).unwrap_err();
+    /// assert_eq!(err, ErrorKind::NotImplemented);
+    ///
+    /// let (spi, _) = Mock::spi("MockSPI").without_log().init();
+    /// let (cs, _) = Mock::pin("MockCS").without_log().init();
+    /// let mut device = Transport::hal(spi)
+    ///     .with_cs(cs)
+    ///     .with_timing(MockDelay, Timing::default())
+    ///     .init();
+    /// SpiDevice::transaction(&mut device, &mut operations).unwrap();
+    /// # }
+    /// ```
+    pub fn with_timing<T: DelayUs<u32>>(
+        self,
+        delay: T,
+        timing: Timing,
+    ) -> HalChipSelectBuilder<SPI, CS, T> {
+        HalChipSelectBuilder {
+            spi: self.spi,
+            cs: self.cs,
+            polarity: self.polarity,
+            delay: Some(delay),
+            timing,
+            max_chunk: self.max_chunk,
+            progress: self.progress,
+        }
+    }
+
+    /// Split transfers into chunks of at most `len` bytes, while the chip
+    /// stays selected.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
+    /// Read the number of bytes exchanged before a failure from the errors
+    /// of the SPI device, for devices which report it. Errors then count
+    /// these bytes, and [`transfer_resumable`](crate::SpiDev::transfer_resumable)
+    /// resumes from the byte which failed.
+    pub fn with_progress(mut self, progress: fn(&SPI::Error) -> Option<usize>) -> Self {
+        self.progress = progress;
+        self
+    }
+
     /// Initialize the transport.
-    pub fn init(self) -> cs::Transport<SPI, CS> {
-        cs::Transport::new(self.spi, self.cs, self.polarity)
+    pub fn init(self) -> cs::Transport<SPI, CS, D> {
+        let timer = Timer::new(self.delay, self.timing);
+        cs::Transport::new(self.spi, self.cs, self.polarity, timer, self.max_chunk)
+            .with_progress(self.progress)
     }
 }
@@ -1,31 +1,98 @@
-use super::super::{Error, Result};
-use crate::{ChipSelect, OutputPin, Polarity, SpiDev, Transfer};
+use super::super::{bit_order, chunk::chunked, timing::Timer, DeviceError, Error, Result};
+use crate::{
+    BitOrder, BitOrderControl, ChipSelect, NoDelay, OutputPin, Polarity, SpiDev, Transfer,
+};
+use embedded_hal::blocking::delay::DelayUs;
 
-pub struct Transport<SPI: Transfer<u8>, CS: OutputPin> {
+#[cfg(feature = "eh1")]
+use core::fmt::Debug;
+
+pub struct Transport<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32> = NoDelay> {
     spi: SPI,
     cs: CS,
     polarity: Polarity,
+    bit_order: BitOrder,
+    timer: Timer<D>,
+    max_chunk: Option<usize>,
+    progress: fn(&SPI::Error) -> Option<usize>,
 }
 
-impl<SPI: Transfer<u8>, CS: OutputPin> Transport<SPI, CS> {
-    pub fn new(spi: SPI, cs: CS, polarity: Polarity) -> Self {
-        let mut transport = Self { spi, cs, polarity };
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> Transport<SPI, CS, D> {
+    pub fn new(
+        spi: SPI,
+        cs: CS,
+        polarity: Polarity,
+        timer: Timer<D>,
+        max_chunk: Option<usize>,
+    ) -> Self {
+        let mut transport = Self {
+            spi,
+            cs,
+            polarity,
+            bit_order: BitOrder::MsbFirst,
+            timer,
+            max_chunk,
+            progress: |_| None,
+        };
 
         transport.deselect().ok();
         transport
     }
+
+    pub(crate) fn with_progress(mut self, progress: fn(&SPI::Error) -> Option<usize>) -> Self {
+        self.progress = progress;
+        self
+    }
 }
 
-impl<SPI: Transfer<u8>, CS: OutputPin> Transfer<u8> for Transport<SPI, CS> {
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> Transfer<u8> for Transport<SPI, CS, D> {
     impl_cs_transfer_common!();
 }
 
-impl<SPI: Transfer<u8>, CS: OutputPin> SpiDev for Transport<SPI, CS> {
-    impl_cs_common!();
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> SpiDev for Transport<SPI, CS, D> {
+    type Source = DeviceError<SPI::Error, CS::Error>;
+
+    impl_cs_common!(timed, DeviceError::Pin);
+    impl_soft_bit_order_common!();
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
 
-    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-        self.spi.transfer(words).or(Err(Error::Transfer))
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Source> {
+        let (spi, order, progress) = (&mut self.spi, self.bit_order, self.progress);
+        chunked(words, self.max_chunk, |chunk| {
+            bit_order::transfer_with(spi, order, chunk, |err| {
+                let error = match progress(&err) {
+                    Some(completed) => Error::Transfer.with_completed(completed),
+                    None => Error::Transfer,
+                };
+                error.with_source(DeviceError::Spi(err))
+            })
+            .and(Ok(()))
+        })
     }
 }
 
-impl<SPI: Transfer<u8>, CS: OutputPin> ChipSelect for Transport<SPI, CS> {}
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> ChipSelect for Transport<SPI, CS, D> {}
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> BitOrderControl for Transport<SPI, CS, D> {}
+
+#[cfg(feature = "eh1")]
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> _eh1::spi::ErrorType
+    for Transport<SPI, CS, D>
+where
+    SPI::Error: Debug,
+    CS::Error: Debug,
+{
+    type Error = Error<<Self as SpiDev>::Source>;
+}
+
+#[cfg(feature = "eh1")]
+impl<SPI: Transfer<u8>, CS: OutputPin, D: DelayUs<u32>> _eh1::spi::SpiDevice
+    for Transport<SPI, CS, D>
+where
+    SPI::Error: Debug,
+    CS::Error: Debug,
+{
+    impl_eh1_device_common!();
+}
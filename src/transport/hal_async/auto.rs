@@ -0,0 +1,30 @@
+use super::super::Result;
+use crate::{AsyncSpiDev, NoDelay};
+use _eh1_async::{delay::DelayNs, spi::SpiBus};
+
+pub struct Transport<SPI: SpiBus<u8>, D: DelayNs = NoDelay> {
+    spi: SPI,
+    delay: Option<D>,
+}
+
+impl<SPI: SpiBus<u8>, D: DelayNs> Transport<SPI, D> {
+    pub fn new(spi: SPI, delay: Option<D>) -> Self {
+        Self { spi, delay }
+    }
+}
+
+impl<SPI: SpiBus<u8>, D: DelayNs> AsyncSpiDev for Transport<SPI, D> {
+    type Source = SPI::Error;
+
+    async fn transfer(&mut self, words: &mut [u8]) -> Result<(), SPI::Error> {
+        self.spi
+            .transfer_in_place(words)
+            .await
+            .map_err(super::transfer_error)?;
+        self.spi.flush().await.map_err(super::transfer_error)
+    }
+
+    async fn delay_us(&mut self, us: u32) -> Result<(), SPI::Error> {
+        super::delay_us(&mut self.delay, us).await
+    }
+}
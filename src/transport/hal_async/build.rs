@@ -0,0 +1,85 @@
+use super::{auto, cs};
+use crate::{NoDelay, OutputPin, Polarity, Transport};
+use _eh1_async::{delay::DelayNs, spi::SpiBus};
+
+impl Transport {
+    /// Construct an async transport from any
+    /// [`embedded_hal_async::spi::SpiBus<u8>`](SpiBus).
+    pub fn hal_async<SPI: SpiBus<u8>>(spi: SPI) -> HalAsyncBuilder<SPI> {
+        HalAsyncBuilder { spi, delay: None }
+    }
+}
+
+pub struct HalAsyncBuilder<SPI: SpiBus<u8>, D: DelayNs = NoDelay> {
+    spi: SPI,
+    delay: Option<D>,
+}
+
+impl<SPI: SpiBus<u8>, D: DelayNs> HalAsyncBuilder<SPI, D> {
+    /// Use the provided chip select pin.
+    pub fn with_cs<CS: OutputPin>(self, cs: CS) -> HalAsyncChipSelectBuilder<SPI, CS, D> {
+        HalAsyncChipSelectBuilder {
+            spi: self.spi,
+            polarity: Polarity::IdleHigh,
+            cs,
+            delay: self.delay,
+            max_chunk: None,
+        }
+    }
+
+    /// Use the provided delay to serve
+    /// [`Operation::DelayUs`](crate::Operation::DelayUs).
+    pub fn with_delay<T: DelayNs>(self, delay: T) -> HalAsyncBuilder<SPI, T> {
+        HalAsyncBuilder {
+            spi: self.spi,
+            delay: Some(delay),
+        }
+    }
+
+    /// Initialize the transport.
+    ///
+    /// Chip select must be handled by the provided SPI device.
+    pub fn init(self) -> auto::Transport<SPI, D> {
+        auto::Transport::new(self.spi, self.delay)
+    }
+}
+
+pub struct HalAsyncChipSelectBuilder<SPI: SpiBus<u8>, CS: OutputPin, D: DelayNs = NoDelay> {
+    spi: SPI,
+    cs: CS,
+    polarity: Polarity,
+    delay: Option<D>,
+    max_chunk: Option<usize>,
+}
+
+impl<SPI: SpiBus<u8>, CS: OutputPin, D: DelayNs> HalAsyncChipSelectBuilder<SPI, CS, D> {
+    /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Use the provided delay to serve
+    /// [`Operation::DelayUs`](crate::Operation::DelayUs).
+    pub fn with_delay<T: DelayNs>(self, delay: T) -> HalAsyncChipSelectBuilder<SPI, CS, T> {
+        HalAsyncChipSelectBuilder {
+            spi: self.spi,
+            cs: self.cs,
+            polarity: self.polarity,
+            delay: Some(delay),
+            max_chunk: self.max_chunk,
+        }
+    }
+
+    /// Split transfers into chunks of at most `len` bytes, while the chip
+    /// stays selected.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
+    /// Initialize the transport.
+    pub fn init(self) -> cs::Transport<SPI, CS, D> {
+        cs::Transport::new(self.spi, self.cs, self.polarity, self.delay, self.max_chunk)
+    }
+}
@@ -0,0 +1,74 @@
+use super::super::{DeviceError, Result};
+use crate::{AsyncSpiDev, NoDelay, OutputPin, Polarity};
+use _eh1_async::{delay::DelayNs, spi::SpiBus};
+
+pub struct Transport<SPI: SpiBus<u8>, CS: OutputPin, D: DelayNs = NoDelay> {
+    spi: SPI,
+    cs: CS,
+    polarity: Polarity,
+    delay: Option<D>,
+    max_chunk: Option<usize>,
+}
+
+impl<SPI: SpiBus<u8>, CS: OutputPin, D: DelayNs> Transport<SPI, CS, D> {
+    pub fn new(
+        spi: SPI,
+        cs: CS,
+        polarity: Polarity,
+        delay: Option<D>,
+        max_chunk: Option<usize>,
+    ) -> Self {
+        let mut transport = Self {
+            spi,
+            cs,
+            polarity,
+            delay,
+            max_chunk,
+        };
+
+        match transport.polarity {
+            Polarity::IdleHigh => transport.cs.set_high(),
+            Polarity::IdleLow => transport.cs.set_low(),
+        }
+        .ok();
+
+        transport
+    }
+}
+
+impl<SPI: SpiBus<u8>, CS: OutputPin, D: DelayNs> AsyncSpiDev for Transport<SPI, CS, D> {
+    type Source = DeviceError<SPI::Error, CS::Error>;
+
+    async fn transfer(&mut self, words: &mut [u8]) -> Result<(), Self::Source> {
+        self.select().await?;
+        self.raw_transfer_or_deselect(words).await?;
+        self.deselect().await
+    }
+
+    impl_cs_common!(async, DeviceError::Pin);
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
+
+    async fn delay_us(&mut self, us: u32) -> Result<(), Self::Source> {
+        super::delay_us(&mut self.delay, us).await
+    }
+
+    /// Split the transfer into chunks of the maximum length, if set.
+    async fn raw_transfer(&mut self, words: &mut [u8]) -> Result<(), Self::Source> {
+        let max = self.max_chunk.filter(|max| *max > 0).unwrap_or(words.len());
+
+        for (index, chunk) in words.chunks_mut(max.max(1)).enumerate() {
+            self.spi
+                .transfer_in_place(chunk)
+                .await
+                .map_err(|err| super::transfer_error(DeviceError::Spi(err)).offset(index * max))?;
+        }
+
+        self.spi
+            .flush()
+            .await
+            .map_err(|err| super::transfer_error(DeviceError::Spi(err)))
+    }
+}
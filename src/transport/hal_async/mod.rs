@@ -0,0 +1,28 @@
+mod auto;
+mod build;
+mod cs;
+
+use super::{Error, ErrorKind, NoDelay, Result};
+use _eh1_async::delay::DelayNs;
+
+/// A failed transfer, keeping the bus error as the source.
+fn transfer_error<E>(err: E) -> Error<E> {
+    Error::Transfer.with_source(err)
+}
+
+/// Wait with the provided delay, if any.
+async fn delay_us<D: DelayNs, E>(delay: &mut Option<D>, us: u32) -> Result<(), E> {
+    match delay {
+        Some(delay) => {
+            delay.delay_us(us).await;
+            Ok(())
+        }
+        None => Err(ErrorKind::NotImplemented.into()),
+    }
+}
+
+/// Transports constructed without a delay return
+/// [`NotImplemented`](ErrorKind::NotImplemented) for delays instead.
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -1,5 +1,11 @@
+pub(crate) mod bit_order;
+pub(crate) mod chunk;
 mod error;
+mod guard;
+mod resume;
+mod timing;
 mod traits;
+mod transaction;
 
 #[macro_use]
 pub mod common;
@@ -10,13 +16,41 @@ pub struct Transport;
 #[cfg(feature = "hal")]
 mod hal;
 
+#[cfg(feature = "hal")]
+mod bitbang;
+
+#[cfg(feature = "hal")]
+pub use bitbang::BitbangError;
+
 #[cfg(feature = "rppal")]
 mod rppal;
 
 #[cfg(feature = "rp2040")]
 mod rp2040;
 
+pub mod shared;
+
+#[cfg(feature = "eh1")]
+pub(crate) mod eh1;
+
+#[cfg(feature = "eh1")]
+pub use eh1::Eh1;
+
+#[cfg(feature = "async")]
+mod async_traits;
+
+#[cfg(feature = "async")]
+mod hal_async;
+
+#[cfg(feature = "async")]
+pub use async_traits::AsyncSpiDev;
+
 pub use {
-    error::{Error, Result},
-    traits::{ChipSelect, ClockSpeed, SpiDev},
+    bit_order::BitOrder,
+    error::{DeviceError, Error, ErrorKind, Result},
+    guard::{SelectGuard, Selected},
+    resume::Resume,
+    timing::{NoDelay, Timing},
+    traits::{BitOrderControl, ChipSelect, ClockSpeed, SpiDev, SpiModeControl, WordSizeControl},
+    transaction::Operation,
 };
@@ -0,0 +1,38 @@
+use super::{Result, SpiDev};
+
+/// How [`SpiDev::transfer_resumable`] splits a transfer into chunks, and how
+/// often it resumes a chunk which failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resume {
+    /// The number of bytes exchanged per selection of the chip. 0 exchanges
+    /// the whole buffer at once.
+    pub chunk_len: usize,
+    /// The number of failures in a row after which the transfer fails.
+    pub retries: usize,
+}
+
+impl Resume {
+    /// Create a strategy.
+    pub const fn new(chunk_len: usize, retries: usize) -> Self {
+        Self { chunk_len, retries }
+    }
+}
+
+/// Exchange one chunk from `offset` in its own selection of the chip. Errors
+/// count the bytes of the chunk exchanged before them, when known.
+pub(crate) fn chunk<S, F>(
+    spi: &mut S,
+    offset: usize,
+    words: &mut [u8],
+    begin: &mut F,
+) -> Result<(), S::Source>
+where
+    S: SpiDev,
+    F: FnMut(&mut S, usize) -> Result<(), S::Source>,
+{
+    spi.select().map_err(|err| err.with_completed(0))?;
+    begin(spi, offset).map_err(|err| err.with_completed(0).after_deselect(spi.deselect()))?;
+    spi.raw_transfer_or_deselect(words)?;
+    spi.deselect()
+        .map_err(|err| err.with_completed(words.len()))
+}
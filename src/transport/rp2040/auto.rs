@@ -1,35 +1,65 @@
-use super::super::{Error, Result};
-use crate::{ClockSpeed, SpiDev, Transfer};
+use super::super::{transaction, Error, Result};
+use crate::{BitOrder, BitOrderControl, ClockSpeed, Operation, SpiDev, Transfer};
+use core::convert::Infallible;
 use embedded_time::rate::{Extensions, Hertz};
 use rp2040_hal::spi::{Enabled, Spi, SpiDevice};
 
 pub struct Transport<D: SpiDevice> {
     spi: Spi<Enabled, D, 8>,
     peripheral_freq: Hertz<u32>,
+    bit_order: BitOrder,
+    max_chunk: Option<usize>,
 }
 
 impl<D: SpiDevice> Transport<D> {
-    pub fn new(spi: Spi<Enabled, D, 8>, peripheral_freq: Hertz<u32>) -> Self {
+    pub fn new(
+        spi: Spi<Enabled, D, 8>,
+        peripheral_freq: Hertz<u32>,
+        max_chunk: Option<usize>,
+    ) -> Self {
         Self {
             spi,
             peripheral_freq,
+            bit_order: BitOrder::MsbFirst,
+            max_chunk,
         }
     }
 }
 
 impl<D: SpiDevice> Transfer<u8> for Transport<D> {
-    impl_auto_transfer_common!();
+    impl_auto_transfer_common!(Infallible, |never: Infallible| match never {});
 }
 
 impl<D: SpiDevice> SpiDev for Transport<D> {
+    type Source = Infallible;
+
     fn is_clock_speed(&self) -> bool {
         true
     }
 
-    fn set_clock_speed(&mut self, speed: u32) -> Result {
+    fn set_clock_speed(&mut self, speed: u32) -> Result<(), Infallible> {
         self.spi.set_baudrate(self.peripheral_freq, speed.Hz());
         Ok(())
     }
+
+    impl_soft_bit_order_common!();
+
+    /// Transfers are not split, since the peripheral selects the chip for
+    /// each, so longer transfers fail with
+    /// [`TooLong`](crate::ErrorKind::TooLong).
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
+
+    /// Runs the operations as a single transfer, so that the chip remains
+    /// selected throughout. Delays would deselect it, so they return
+    /// [`NotImplemented`](crate::ErrorKind::NotImplemented).
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Infallible> {
+        transaction::grouped(operations, |words| {
+            Transfer::transfer(self, words).and(Ok(()))
+        })
+    }
 }
 
 impl<D: SpiDevice> ClockSpeed for Transport<D> {}
+impl<D: SpiDevice> BitOrderControl for Transport<D> {}
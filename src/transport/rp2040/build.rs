@@ -1,5 +1,6 @@
-use super::{auto, cs};
-use crate::{Polarity, Transport};
+use super::{super::timing::Timer, auto, cs};
+use crate::{NoDelay, Polarity, Timing, Transport};
+use embedded_hal::blocking::delay::DelayUs;
 use embedded_time::rate::Hertz;
 use rp2040_hal::{
     gpio::{Pin, PinId, PushPullOutput},
@@ -15,6 +16,7 @@ impl Transport {
         Rp2040Builder {
             spi,
             peripheral_freq: peripheral_freq.into(),
+            max_chunk: None,
         }
     }
 }
@@ -22,6 +24,7 @@ impl Transport {
 pub struct Rp2040Builder<D: SpiDevice> {
     spi: Spi<Enabled, D, 8>,
     peripheral_freq: Hertz,
+    max_chunk: Option<usize>,
 }
 
 impl<D: SpiDevice> Rp2040Builder<D> {
@@ -33,31 +36,81 @@ impl<D: SpiDevice> Rp2040Builder<D> {
             peripheral_freq: self.peripheral_freq,
             polarity: Polarity::IdleHigh,
             cs,
+            delay: None,
+            timing: Timing::default(),
+            max_chunk: self.max_chunk,
         }
     }
 
+    /// Advertise the most bytes the device exchanges at once. Since the
+    /// peripheral selects the chip for each transfer, longer transfers fail
+    /// with [`TooLong`](crate::ErrorKind::TooLong) rather than being split.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
     /// Initialize the transport.
     pub fn init(self) -> auto::Transport<D> {
-        auto::Transport::new(self.spi, self.peripheral_freq)
+        auto::Transport::new(self.spi, self.peripheral_freq, self.max_chunk)
     }
 }
 
-pub struct Rp2040ChipSelectBuilder<D: SpiDevice, P: PinId> {
+pub struct Rp2040ChipSelectBuilder<D: SpiDevice, P: PinId, T: DelayUs<u32> = NoDelay> {
     spi: Spi<Enabled, D, 8>,
     peripheral_freq: Hertz,
     cs: Pin<P, PushPullOutput>,
     polarity: Polarity,
+    delay: Option<T>,
+    timing: Timing,
+    max_chunk: Option<usize>,
 }
 
-impl<D: SpiDevice, P: PinId> Rp2040ChipSelectBuilder<D, P> {
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> Rp2040ChipSelectBuilder<D, P, T> {
     /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
     pub fn with_polarity(mut self, polarity: Polarity) -> Self {
         self.polarity = polarity;
         self
     }
 
+    /// Use the provided delay (such as `cortex_m::delay::Delay`) to satisfy
+    /// the chip select timing on every select and deselect. The delay also
+    /// serves [`Operation::DelayUs`](crate::Operation::DelayUs) and the
+    /// `DelayNs` operations of embedded-hal 1.0 transactions, which fail with
+    /// [`NotImplemented`](crate::ErrorKind::NotImplemented) without it.
+    pub fn with_timing<U: DelayUs<u32>>(
+        self,
+        delay: U,
+        timing: Timing,
+    ) -> Rp2040ChipSelectBuilder<D, P, U> {
+        Rp2040ChipSelectBuilder {
+            spi: self.spi,
+            peripheral_freq: self.peripheral_freq,
+            cs: self.cs,
+            polarity: self.polarity,
+            delay: Some(delay),
+            timing,
+            max_chunk: self.max_chunk,
+        }
+    }
+
+    /// Split transfers into chunks of at most `len` bytes, while the chip
+    /// stays selected.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = Some(len);
+        self
+    }
+
     /// Initialize the transport.
-    pub fn init(self) -> cs::Transport<D, P> {
-        cs::Transport::new(self.spi, self.peripheral_freq, self.cs, self.polarity)
+    pub fn init(self) -> cs::Transport<D, P, T> {
+        let timer = Timer::new(self.delay, self.timing);
+        cs::Transport::new(
+            self.spi,
+            self.peripheral_freq,
+            self.cs,
+            self.polarity,
+            timer,
+            self.max_chunk,
+        )
     }
 }
@@ -1,30 +1,43 @@
-use super::super::{Error, Result};
-use crate::{ChipSelect, ClockSpeed, OutputPin, Polarity, SpiDev, Transfer};
+use super::super::{bit_order, chunk::chunked, timing::Timer, Error, Result};
+use crate::{
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, NoDelay, OutputPin, Polarity, SpiDev,
+    Transfer,
+};
+use core::convert::Infallible;
+use embedded_hal::blocking::delay::DelayUs;
 use embedded_time::rate::{Extensions, Hertz};
 use rp2040_hal::{
     gpio::{Pin, PinId, PushPullOutput},
     spi::{Enabled, Spi, SpiDevice},
 };
 
-pub struct Transport<D: SpiDevice, P: PinId> {
+pub struct Transport<D: SpiDevice, P: PinId, T: DelayUs<u32> = NoDelay> {
     spi: Spi<Enabled, D, 8>,
     peripheral_freq: Hertz<u32>,
     cs: Pin<P, PushPullOutput>,
     polarity: Polarity,
+    bit_order: BitOrder,
+    timer: Timer<T>,
+    max_chunk: Option<usize>,
 }
 
-impl<D: SpiDevice, P: PinId> Transport<D, P> {
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> Transport<D, P, T> {
     pub fn new(
         spi: Spi<Enabled, D, 8>,
         peripheral_freq: Hertz<u32>,
         cs: Pin<P, PushPullOutput>,
         polarity: Polarity,
+        timer: Timer<T>,
+        max_chunk: Option<usize>,
     ) -> Self {
         let mut transport = Self {
             spi,
             peripheral_freq,
             cs,
             polarity,
+            bit_order: BitOrder::MsbFirst,
+            timer,
+            max_chunk,
         };
 
         transport.deselect().ok();
@@ -32,26 +45,47 @@ impl<D: SpiDevice, P: PinId> Transport<D, P> {
     }
 }
 
-impl<D: SpiDevice, P: PinId> SpiDev for Transport<D, P> {
-    impl_cs_common!();
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> SpiDev for Transport<D, P, T> {
+    type Source = Infallible;
+
+    impl_cs_common!(timed, |never: Infallible| match never {});
+    impl_soft_bit_order_common!();
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.max_chunk
+    }
 
-    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-        self.spi.transfer(words).or(Err(Error::Transfer))
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Infallible> {
+        let (spi, order) = (&mut self.spi, self.bit_order);
+        chunked(words, self.max_chunk, |chunk| {
+            bit_order::transfer_with(spi, order, chunk, |never| match never {}).and(Ok(()))
+        })
     }
 
     fn is_clock_speed(&self) -> bool {
         true
     }
 
-    fn set_clock_speed(&mut self, speed: u32) -> Result {
+    fn set_clock_speed(&mut self, speed: u32) -> Result<(), Infallible> {
         self.spi.set_baudrate(self.peripheral_freq, speed.Hz());
         Ok(())
     }
 }
 
-impl<D: SpiDevice, P: PinId> Transfer<u8> for Transport<D, P> {
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> Transfer<u8> for Transport<D, P, T> {
     impl_cs_transfer_common!();
 }
 
-impl<D: SpiDevice, P: PinId> ChipSelect for Transport<D, P> {}
-impl<D: SpiDevice, P: PinId> ClockSpeed for Transport<D, P> {}
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> ChipSelect for Transport<D, P, T> {}
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> ClockSpeed for Transport<D, P, T> {}
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> BitOrderControl for Transport<D, P, T> {}
+
+#[cfg(feature = "eh1")]
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> _eh1::spi::ErrorType for Transport<D, P, T> {
+    type Error = Error<Infallible>;
+}
+
+#[cfg(feature = "eh1")]
+impl<D: SpiDevice, P: PinId, T: DelayUs<u32>> _eh1::spi::SpiDevice for Transport<D, P, T> {
+    impl_eh1_device_common!();
+}
@@ -1,33 +1,251 @@
-use super::super::{Error, Result};
-use crate::{ClockSpeed, SpiDev, Transfer};
-use _rppal::spi::Spi;
+use super::super::{bit_order, chunk, Error, Result};
+use crate::{
+    BitOrder, BitOrderControl, ClockSpeed, Mode, Operation, Polarity, SpiDev, SpiModeControl,
+    Transfer, WordSizeControl,
+};
+use _rppal::spi::{self, Segment, Spi};
+use std::{thread, time::Duration, vec::Vec};
 
 pub struct Transport {
     spi: Spi,
+    bit_order: BitOrder,
+    max_chunk: usize,
 }
 
 impl Transport {
-    pub fn new(spi: Spi) -> Self {
-        Self { spi }
+    pub fn new(spi: Spi, max_chunk: usize) -> Self {
+        Self {
+            spi,
+            bit_order: BitOrder::MsbFirst,
+            max_chunk,
+        }
+    }
+
+    /// Set the polarity of the chip select line which spidev drives.
+    /// [IdleHigh](Polarity::IdleHigh) selects the chip by driving it low,
+    /// which is the default.
+    pub fn set_ss_polarity(&mut self, polarity: Polarity) -> Result<(), spi::Error> {
+        let polarity = match polarity {
+            Polarity::IdleHigh => spi::Polarity::ActiveLow,
+            Polarity::IdleLow => spi::Polarity::ActiveHigh,
+        };
+
+        self.spi
+            .set_ss_polarity(polarity)
+            .map_err(|err| Error::ChipSelect.with_source(err))
     }
 }
 
 impl Transfer<u8> for Transport {
-    type Error = Error;
+    type Error = Error<spi::Error>;
 
-    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-        <Spi as Transfer<u8>>::transfer(&mut self.spi, words).or(Err(Error::Transfer))
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], spi::Error> {
+        chunk::check_len(words.len(), Some(self.max_chunk))?;
+        bit_order::transfer_with(&mut self.spi, self.bit_order, words, super::transfer_error)
     }
 }
 
 impl SpiDev for Transport {
+    type Source = spi::Error;
+
     fn is_clock_speed(&self) -> bool {
         true
     }
 
-    fn set_clock_speed(&mut self, speed: u32) -> Result {
-        self.spi.set_clock_speed(speed).or(Err(Error::ClockSpeed))
+    fn is_spi_mode(&self) -> bool {
+        true
+    }
+
+    fn is_bit_order(&self) -> bool {
+        true
+    }
+
+    fn is_word_size(&self) -> bool {
+        true
+    }
+
+    /// Transfers are not split, since spidev selects the chip for each, so
+    /// longer transfers and transactions fail with
+    /// [`TooLong`](crate::ErrorKind::TooLong).
+    fn max_transfer_len(&self) -> Option<usize> {
+        Some(self.max_chunk)
+    }
+
+    fn set_clock_speed(&mut self, speed: u32) -> Result<(), spi::Error> {
+        self.spi
+            .set_clock_speed(speed)
+            .map_err(|err| Error::ClockSpeed.with_source(err))
+    }
+
+    fn set_spi_mode(&mut self, mode: Mode) -> Result<(), spi::Error> {
+        self.spi
+            .set_mode(super::spi_mode(mode))
+            .map_err(|err| Error::SpiMode.with_source(err))
+    }
+
+    /// Set the bit order in hardware, or emulate it in software if the
+    /// hardware does not support it.
+    fn set_bit_order(&mut self, order: BitOrder) -> Result<(), spi::Error> {
+        self.bit_order = super::set_bit_order(&self.spi, order);
+        Ok(())
+    }
+
+    /// Set the bits per word of spidev. The Raspberry Pi only supports 8.
+    fn set_word_size(&mut self, bits: u8) -> Result<(), spi::Error> {
+        self.spi
+            .set_bits_per_word(bits)
+            .map_err(|err| Error::WordSize.with_source(err))
+    }
+
+    fn delay_us(&mut self, us: u32) -> Result<(), spi::Error> {
+        thread::sleep(Duration::from_micros(us.into()));
+        Ok(())
+    }
+
+    /// Runs the operations as a single group of [`Segment`]s, so that the
+    /// chip remains selected throughout.
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), spi::Error> {
+        // spidev limits the bytes of all the segments together
+        let len = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Write(words) => words.len(),
+                Operation::Read(words) | Operation::Transfer(words) => words.len(),
+                Operation::DelayUs(_) => 0,
+            })
+            .sum();
+        chunk::check_len(len, Some(self.max_chunk))?;
+
+        let reverse = self.bit_order == BitOrder::LsbFirst;
+        let writes: Vec<Vec<u8>> = operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Transfer(words) => reversed(words, reverse),
+                Operation::Write(words) if reverse => reversed(words, reverse),
+                _ => Vec::new(),
+            })
+            .collect();
+
+        let mut segments = Vec::with_capacity(operations.len());
+
+        for (operation, write) in operations.iter_mut().zip(&writes) {
+            match operation {
+                Operation::Write(_) if reverse => segments.push(Segment::with_write(write)),
+                Operation::Write(words) => segments.push(Segment::with_write(words)),
+                Operation::Read(words) => segments.push(Segment::with_read(words)),
+                Operation::Transfer(words) => segments.push(Segment::new(words, write)),
+                Operation::DelayUs(us) if segments.is_empty() => self.delay_us(*us)?,
+                Operation::DelayUs(us) => delay(&mut segments, *us),
+            }
+        }
+
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        self.spi
+            .transfer_segments(&segments)
+            .map_err(super::transfer_error)?;
+
+        drop(segments);
+
+        if reverse {
+            for operation in operations.iter_mut() {
+                if let Operation::Read(words) | Operation::Transfer(words) = operation {
+                    words
+                        .iter_mut()
+                        .for_each(|word| *word = word.reverse_bits());
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
 impl ClockSpeed for Transport {}
+impl SpiModeControl for Transport {}
+impl BitOrderControl for Transport {}
+impl WordSizeControl for Transport {}
+
+/// Wait after the last segment. Delays longer than a segment allows are
+/// spread over empty segments, during which the chip stays selected.
+fn delay(segments: &mut Vec<Segment<'_, '_>>, us: u32) {
+    let mut remaining = us;
+
+    while remaining > 0 {
+        if segments
+            .last()
+            .is_none_or(|segment| segment.delay() == u16::MAX)
+        {
+            segments.push(Segment::with_write(&[]));
+        }
+
+        let segment = segments.last_mut().unwrap();
+        let added = remaining.min(u32::from(u16::MAX - segment.delay()));
+        segment.set_delay(segment.delay() + added as u16);
+        remaining -= added;
+    }
+}
+
+/// Copy the bytes, reversing the bits of each if required.
+fn reversed(words: &[u8], reverse: bool) -> Vec<u8> {
+    match reverse {
+        true => words.iter().map(|word| word.reverse_bits()).collect(),
+        false => words.to_vec(),
+    }
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::ErrorType for Transport {
+    type Error = Error<spi::Error>;
+}
+
+#[cfg(feature = "eh1")]
+impl _eh1::spi::SpiDevice for Transport {
+    /// Runs the operations as a single group of [`Segment`]s, like
+    /// [`SpiDev::transaction`], so that the chip remains selected
+    /// throughout.
+    fn transaction(
+        &mut self,
+        operations: &mut [_eh1::spi::Operation<'_, u8>],
+    ) -> Result<(), spi::Error> {
+        use _eh1::spi::Operation as SpiOperation;
+
+        let mut buffers: Vec<Vec<u8>> = operations
+            .iter()
+            .map(|operation| match operation {
+                SpiOperation::Transfer(read, write) => {
+                    let mut buffer = write.to_vec();
+                    buffer.resize(read.len().max(write.len()), 0x00);
+                    buffer
+                }
+                _ => Vec::new(),
+            })
+            .collect();
+
+        let mut grouped: Vec<Operation<'_>> = operations
+            .iter_mut()
+            .zip(&mut buffers)
+            .map(|(operation, buffer)| match operation {
+                SpiOperation::Read(words) => Operation::Read(words),
+                SpiOperation::Write(words) => Operation::Write(words),
+                SpiOperation::TransferInPlace(words) => Operation::Transfer(words),
+                SpiOperation::Transfer(..) => Operation::Transfer(buffer),
+                SpiOperation::DelayNs(ns) => Operation::DelayUs(ns.div_ceil(1000)),
+            })
+            .collect();
+
+        SpiDev::transaction(self, &mut grouped)?;
+        drop(grouped);
+
+        for (operation, buffer) in operations.iter_mut().zip(&buffers) {
+            if let SpiOperation::Transfer(read, _) = operation {
+                let len = read.len();
+                read.copy_from_slice(&buffer[..len]);
+            }
+        }
+
+        Ok(())
+    }
+}
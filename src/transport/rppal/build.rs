@@ -1,16 +1,21 @@
-use super::{auto, cs};
-use crate::{Polarity, Transport};
+use super::{super::timing::Timer, auto, cs};
+use crate::{NoDelay, Polarity, Timing, Transport};
 use _rppal::{gpio::OutputPin, spi::Spi};
+use embedded_hal::blocking::delay::DelayUs;
 
 impl Transport {
     /// Construct a transport from an [`rppal::spi::Spi`](Spi).
     pub fn rppal(spi: Spi) -> RppalBuilder {
-        RppalBuilder { spi }
+        RppalBuilder {
+            spi,
+            max_chunk: super::BUFFER_SIZE,
+        }
     }
 }
 
 pub struct RppalBuilder {
     spi: Spi,
+    max_chunk: usize,
 }
 
 impl RppalBuilder {
@@ -20,30 +25,70 @@ impl RppalBuilder {
             spi: self.spi,
             polarity: Polarity::IdleHigh,
             cs,
+            delay: None,
+            timing: Timing::default(),
+            max_chunk: self.max_chunk,
         }
     }
 
+    /// Use the size of the spidev buffer, if changed from the default of
+    /// 4096 bytes. Since spidev selects the chip for each transfer, longer
+    /// transfers fail with [`TooLong`](crate::ErrorKind::TooLong) rather
+    /// than being split.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = len;
+        self
+    }
+
     /// Initialize the transport.
     pub fn init(self) -> auto::Transport {
-        auto::Transport::new(self.spi)
+        auto::Transport::new(self.spi, self.max_chunk)
     }
 }
 
-pub struct RppalChipSelectBuilder {
+pub struct RppalChipSelectBuilder<D: DelayUs<u32> = NoDelay> {
     spi: Spi,
     cs: OutputPin,
     polarity: Polarity,
+    delay: Option<D>,
+    timing: Timing,
+    max_chunk: usize,
 }
 
-impl RppalChipSelectBuilder {
+impl<D: DelayUs<u32>> RppalChipSelectBuilder<D> {
     /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
     pub fn with_polarity(mut self, polarity: Polarity) -> Self {
         self.polarity = polarity;
         self
     }
 
+    /// Use the provided delay (such as `rppal::hal::Delay`) to satisfy the
+    /// chip select timing on every select and deselect.
+    pub fn with_timing<T: DelayUs<u32>>(
+        self,
+        delay: T,
+        timing: Timing,
+    ) -> RppalChipSelectBuilder<T> {
+        RppalChipSelectBuilder {
+            spi: self.spi,
+            cs: self.cs,
+            polarity: self.polarity,
+            delay: Some(delay),
+            timing,
+            max_chunk: self.max_chunk,
+        }
+    }
+
+    /// Split transfers into chunks of at most `len` bytes, while the chip
+    /// stays selected. Defaults to the spidev buffer size of 4096 bytes.
+    pub fn with_max_chunk(mut self, len: usize) -> Self {
+        self.max_chunk = len;
+        self
+    }
+
     /// Initialize the transport.
-    pub fn init(self) -> cs::Transport {
-        cs::Transport::new(self.spi, self.cs, self.polarity)
+    pub fn init(self) -> cs::Transport<D> {
+        let timer = Timer::new(self.delay, self.timing);
+        cs::Transport::new(self.spi, self.cs, self.polarity, timer, self.max_chunk)
     }
 }
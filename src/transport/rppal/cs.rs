@@ -1,61 +1,154 @@
-use super::super::{Error, Result};
-use crate::{ChipSelect, ClockSpeed, Polarity, SpiDev, Transfer};
-use _rppal::{gpio::OutputPin as RpPin, spi::Spi};
+use super::super::{bit_order, chunk::chunked, timing::Timer, Error, Result};
+use crate::{
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, Mode, NoDelay, Polarity, SpiDev,
+    SpiModeControl, Transfer, WordSizeControl,
+};
+use _rppal::{
+    gpio::OutputPin as RpPin,
+    spi::{self, Spi},
+};
+use embedded_hal::blocking::delay::DelayUs;
+use std::{thread, time::Duration};
 
-pub struct Transport {
+pub struct Transport<D: DelayUs<u32> = NoDelay> {
     spi: Spi,
     cs: RpPin,
     polarity: Polarity,
+    bit_order: BitOrder,
+    timer: Timer<D>,
+    max_chunk: usize,
 }
 
-impl Transport {
-    pub fn new(spi: Spi, cs: RpPin, polarity: Polarity) -> Self {
-        let mut transport = Self { spi, cs, polarity };
+impl<D: DelayUs<u32>> Transport<D> {
+    pub fn new(spi: Spi, cs: RpPin, polarity: Polarity, timer: Timer<D>, max_chunk: usize) -> Self {
+        let mut transport = Self {
+            spi,
+            cs,
+            polarity,
+            bit_order: BitOrder::MsbFirst,
+            timer,
+            max_chunk,
+        };
 
         transport.deselect().ok();
         transport
     }
 }
 
-impl SpiDev for Transport {
+impl<D: DelayUs<u32>> SpiDev for Transport<D> {
+    type Source = spi::Error;
+
     fn is_chip_select(&self) -> bool {
         true
     }
 
-    fn select(&mut self) -> Result {
-        match self.polarity {
-            Polarity::IdleHigh => self.cs.set_low(),
-            Polarity::IdleLow => self.cs.set_high(),
-        };
+    fn select(&mut self) -> Result<(), spi::Error> {
+        let (cs, polarity) = (&mut self.cs, self.polarity);
 
-        Ok(())
+        self.timer.select(|| {
+            match polarity {
+                Polarity::IdleHigh => cs.set_low(),
+                Polarity::IdleLow => cs.set_high(),
+            };
+
+            Ok(())
+        })
     }
 
-    fn deselect(&mut self) -> Result {
-        match self.polarity {
-            Polarity::IdleHigh => self.cs.set_high(),
-            Polarity::IdleLow => self.cs.set_low(),
-        };
+    fn deselect(&mut self) -> Result<(), spi::Error> {
+        let (cs, polarity) = (&mut self.cs, self.polarity);
 
-        Ok(())
+        self.timer.deselect(|| {
+            match polarity {
+                Polarity::IdleHigh => cs.set_high(),
+                Polarity::IdleLow => cs.set_low(),
+            };
+
+            Ok(())
+        })
     }
 
-    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-        <Spi as Transfer<u8>>::transfer(&mut self.spi, words).or(Err(Error::Transfer))
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], spi::Error> {
+        let (spi, order) = (&mut self.spi, self.bit_order);
+        chunked(words, Some(self.max_chunk), |chunk| {
+            bit_order::transfer_with(spi, order, chunk, super::transfer_error).and(Ok(()))
+        })
     }
 
     fn is_clock_speed(&self) -> bool {
         true
     }
 
-    fn set_clock_speed(&mut self, speed: u32) -> Result {
-        self.spi.set_clock_speed(speed).or(Err(Error::ClockSpeed))
+    fn is_spi_mode(&self) -> bool {
+        true
+    }
+
+    fn is_bit_order(&self) -> bool {
+        true
+    }
+
+    fn is_word_size(&self) -> bool {
+        true
+    }
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        Some(self.max_chunk)
+    }
+
+    fn set_clock_speed(&mut self, speed: u32) -> Result<(), spi::Error> {
+        self.spi
+            .set_clock_speed(speed)
+            .map_err(|err| Error::ClockSpeed.with_source(err))
+    }
+
+    fn set_spi_mode(&mut self, mode: Mode) -> Result<(), spi::Error> {
+        self.spi
+            .set_mode(super::spi_mode(mode))
+            .map_err(|err| Error::SpiMode.with_source(err))
+    }
+
+    /// Set the bit order in hardware, or emulate it in software if the
+    /// hardware does not support it.
+    fn set_bit_order(&mut self, order: BitOrder) -> Result<(), spi::Error> {
+        self.bit_order = super::set_bit_order(&self.spi, order);
+        Ok(())
+    }
+
+    /// Set the bits per word of spidev. The Raspberry Pi only supports 8.
+    fn set_word_size(&mut self, bits: u8) -> Result<(), spi::Error> {
+        self.spi
+            .set_bits_per_word(bits)
+            .map_err(|err| Error::WordSize.with_source(err))
+    }
+
+    /// Wait using the delay provided for chip select timing, or sleep the
+    /// thread if there is none.
+    fn delay_us(&mut self, us: u32) -> Result<(), spi::Error> {
+        if self.timer.is_delay() {
+            return self.timer.delay_us(us);
+        }
+
+        thread::sleep(Duration::from_micros(us.into()));
+        Ok(())
     }
 }
 
-impl Transfer<u8> for Transport {
+impl<D: DelayUs<u32>> Transfer<u8> for Transport<D> {
     impl_cs_transfer_common!();
 }
 
-impl ChipSelect for Transport {}
-impl ClockSpeed for Transport {}
+impl<D: DelayUs<u32>> ChipSelect for Transport<D> {}
+impl<D: DelayUs<u32>> ClockSpeed for Transport<D> {}
+impl<D: DelayUs<u32>> SpiModeControl for Transport<D> {}
+impl<D: DelayUs<u32>> BitOrderControl for Transport<D> {}
+impl<D: DelayUs<u32>> WordSizeControl for Transport<D> {}
+
+#[cfg(feature = "eh1")]
+impl<D: DelayUs<u32>> _eh1::spi::ErrorType for Transport<D> {
+    type Error = Error<spi::Error>;
+}
+
+#[cfg(feature = "eh1")]
+impl<D: DelayUs<u32>> _eh1::spi::SpiDevice for Transport<D> {
+    impl_eh1_device_common!();
+}
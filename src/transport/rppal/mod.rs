@@ -1,3 +1,41 @@
 mod auto;
 mod build;
 mod cs;
+
+use super::Error;
+use crate::{BitOrder, Mode, Polarity};
+use _rppal::spi;
+use embedded_hal::spi::Phase;
+
+/// The default size of the spidev buffer, which limits the length of each
+/// transfer.
+const BUFFER_SIZE: usize = 4096;
+
+/// Convert an SPI mode to its [`rppal`](_rppal) equivalent.
+fn spi_mode(mode: Mode) -> spi::Mode {
+    match (mode.polarity, mode.phase) {
+        (Polarity::IdleLow, Phase::CaptureOnFirstTransition) => spi::Mode::Mode0,
+        (Polarity::IdleLow, Phase::CaptureOnSecondTransition) => spi::Mode::Mode1,
+        (Polarity::IdleHigh, Phase::CaptureOnFirstTransition) => spi::Mode::Mode2,
+        (Polarity::IdleHigh, Phase::CaptureOnSecondTransition) => spi::Mode::Mode3,
+    }
+}
+
+/// Set the bit order in hardware where supported. Returns the order which
+/// must be emulated in software.
+fn set_bit_order(spi: &spi::Spi, order: BitOrder) -> BitOrder {
+    let hardware = match order {
+        BitOrder::MsbFirst => spi::BitOrder::MsbFirst,
+        BitOrder::LsbFirst => spi::BitOrder::LsbFirst,
+    };
+
+    match spi.set_bit_order(hardware) {
+        Ok(_) => BitOrder::MsbFirst,
+        Err(_) => order,
+    }
+}
+
+/// A failed transfer, keeping the [`rppal`](_rppal) error as the source.
+fn transfer_error(err: spi::Error) -> Error<spi::Error> {
+    Error::Transfer.with_source(err)
+}
@@ -0,0 +1,68 @@
+use super::{device, BusMutex, SharedBus};
+use crate::{BitOrder, Mode, OutputPin, Polarity, SpiDev};
+
+impl<M: BusMutex> SharedBus<M>
+where
+    M::Bus: SpiDev,
+{
+    /// Add a device to the bus, using the provided chip select pin.
+    pub fn device<CS: OutputPin>(&self, cs: CS) -> SharedDeviceBuilder<'_, M, CS> {
+        SharedDeviceBuilder {
+            bus: self,
+            cs,
+            polarity: Polarity::IdleHigh,
+            config: device::Config::default(),
+        }
+    }
+}
+
+pub struct SharedDeviceBuilder<'a, M: BusMutex, CS: OutputPin> {
+    bus: &'a SharedBus<M>,
+    cs: CS,
+    polarity: Polarity,
+    config: device::Config,
+}
+
+impl<'a, M: BusMutex, CS: OutputPin> SharedDeviceBuilder<'a, M, CS>
+where
+    M::Bus: SpiDev,
+{
+    /// Use the provided polarity. Defaults to [IdleHigh](Polarity::IdleHigh).
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Apply the clock speed to the bus before each transfer. The bus must
+    /// implement [`ClockSpeed`](crate::ClockSpeed).
+    pub fn with_clock_speed(mut self, speed: u32) -> Self {
+        self.config.clock_speed = Some(speed);
+        self
+    }
+
+    /// Apply the SPI mode to the bus before each transfer. The bus must
+    /// implement [`SpiModeControl`](crate::SpiModeControl).
+    pub fn with_spi_mode(mut self, mode: Mode) -> Self {
+        self.config.spi_mode = Some(mode);
+        self
+    }
+
+    /// Apply the bit order to the bus before each transfer. The bus must
+    /// implement [`BitOrderControl`](crate::BitOrderControl).
+    pub fn with_bit_order(mut self, order: BitOrder) -> Self {
+        self.config.bit_order = Some(order);
+        self
+    }
+
+    /// Apply the word size to the bus before each transfer. The bus must
+    /// implement [`WordSizeControl`](crate::WordSizeControl).
+    pub fn with_word_size(mut self, bits: u8) -> Self {
+        self.config.word_size = Some(bits);
+        self
+    }
+
+    /// Initialize the device.
+    pub fn init(self) -> device::Device<'a, M, CS> {
+        device::Device::new(self.bus, self.cs, self.polarity, self.config)
+    }
+}
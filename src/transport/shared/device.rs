@@ -0,0 +1,319 @@
+use super::super::{chunk::chunked, DeviceError, Error, ErrorKind, Result};
+use super::{BusMutex, SharedBus};
+use crate::{
+    BitOrder, BitOrderControl, ChipSelect, ClockSpeed, Mode, Operation, OutputPin, Polarity,
+    SpiDev, SpiModeControl, Transfer, WordSizeControl,
+};
+use core::sync::atomic::Ordering;
+
+/// The owner of a bus which no device holds.
+pub const FREE: usize = 0;
+
+/// The source of errors from a device on a bus `B` with chip select pin `CS`.
+type Source<B, CS> = DeviceError<<B as SpiDev>::Source, <CS as OutputPin>::Error>;
+
+/// A device on a [`SharedBus`], selected by its own pin.
+pub struct Device<'a, M: BusMutex, CS: OutputPin> {
+    bus: &'a SharedBus<M>,
+    id: usize,
+    cs: CS,
+    polarity: Polarity,
+    config: Config,
+}
+
+/// Bus settings applied before each transfer, where set.
+#[derive(Clone, Copy, Default)]
+pub struct Config {
+    pub clock_speed: Option<u32>,
+    pub spi_mode: Option<Mode>,
+    pub bit_order: Option<BitOrder>,
+    pub word_size: Option<u8>,
+}
+
+impl Config {
+    fn apply<B: SpiDev>(&self, spi: &mut B) -> Result<(), B::Source> {
+        if let Some(speed) = self.clock_speed {
+            spi.set_clock_speed(speed)?;
+        }
+
+        if let Some(mode) = self.spi_mode {
+            spi.set_spi_mode(mode)?;
+        }
+
+        if let Some(order) = self.bit_order {
+            spi.set_bit_order(order)?;
+        }
+
+        if let Some(bits) = self.word_size {
+            spi.set_word_size(bits)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, M: BusMutex, CS: OutputPin> Device<'a, M, CS>
+where
+    M::Bus: SpiDev,
+{
+    pub fn new(bus: &'a SharedBus<M>, cs: CS, polarity: Polarity, config: Config) -> Self {
+        // The claim is only read and written under the lock, so no
+        // compare-and-swap is needed
+        let id = bus.bus.lock(|_| {
+            let id = bus.devices.load(Ordering::Relaxed) + 1;
+            bus.devices.store(id, Ordering::Relaxed);
+            id
+        });
+
+        let mut device = Self {
+            bus,
+            id,
+            cs,
+            polarity,
+            config,
+        };
+
+        device.deselect().ok();
+        device
+    }
+
+    /// Lock the bus once no other device holds it, and apply the device
+    /// settings unless this device already holds it, before running `f`.
+    fn with_bus<R>(
+        &mut self,
+        f: impl FnOnce(&mut Locked<'_, M::Bus, CS>) -> Result<R, Source<M::Bus, CS>>,
+    ) -> Result<R, Source<M::Bus, CS>> {
+        let (bus, id) = (self.bus, self.id);
+        let (cs, polarity, config) = (&mut self.cs, self.polarity, self.config);
+        let owner = || bus.owner.load(Ordering::Relaxed);
+
+        bus.bus
+            .lock_when(
+                || owner() == FREE || owner() == id,
+                |spi| {
+                    if owner() != id {
+                        config
+                            .apply(spi)
+                            .map_err(|err| err.map_source(DeviceError::Spi))?;
+                    }
+                    f(&mut Locked { spi, cs, polarity })
+                },
+            )
+            .unwrap_or(Err(ErrorKind::BusBusy.into()))
+    }
+}
+
+impl<M: BusMutex, CS: OutputPin> Device<'_, M, CS> {
+    /// Whether this device has its chip selected, holding the bus.
+    fn is_owner(&self) -> bool {
+        self.bus.owner.load(Ordering::Relaxed) == self.id
+    }
+
+    /// Release the bus if this device holds it.
+    fn release(&mut self) {
+        let released = self.bus.bus.lock(|_| {
+            let owner = self.is_owner();
+            if owner {
+                self.bus.owner.store(FREE, Ordering::Relaxed);
+            }
+            owner
+        });
+
+        if released {
+            self.bus.bus.notify();
+        }
+    }
+}
+
+impl<M: BusMutex, CS: OutputPin> Drop for Device<'_, M, CS> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl<M: BusMutex, CS: OutputPin> Transfer<u8> for Device<'_, M, CS>
+where
+    M::Bus: SpiDev,
+{
+    type Error = Error<Source<M::Bus, CS>>;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Source<M::Bus, CS>> {
+        self.with_bus(|locked| locked.transfer(words))
+    }
+}
+
+impl<M: BusMutex, CS: OutputPin> SpiDev for Device<'_, M, CS>
+where
+    M::Bus: SpiDev,
+{
+    type Source = Source<M::Bus, CS>;
+
+    fn is_chip_select(&self) -> bool {
+        true
+    }
+
+    fn is_clock_speed(&self) -> bool {
+        self.bus.bus.lock(|spi| spi.is_clock_speed())
+    }
+
+    fn is_spi_mode(&self) -> bool {
+        self.bus.bus.lock(|spi| spi.is_spi_mode())
+    }
+
+    fn is_bit_order(&self) -> bool {
+        self.bus.bus.lock(|spi| spi.is_bit_order())
+    }
+
+    fn is_word_size(&self) -> bool {
+        self.bus.bus.lock(|spi| spi.is_word_size())
+    }
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.bus.bus.lock(|spi| spi.max_transfer_len())
+    }
+
+    /// Wait for the bus, apply the device settings, then select the chip.
+    /// The device holds the bus until it deselects the chip.
+    fn select(&mut self) -> Result<(), Self::Source> {
+        let (bus, id) = (self.bus, self.id);
+        self.with_bus(|locked| {
+            locked.select()?;
+            bus.owner.store(id, Ordering::Relaxed);
+            Ok(())
+        })
+    }
+
+    /// Deselect the chip and release the bus.
+    fn deselect(&mut self) -> Result<(), Self::Source> {
+        let result = match self.polarity {
+            Polarity::IdleHigh => self.cs.set_high(),
+            Polarity::IdleLow => self.cs.set_low(),
+        }
+        .map_err(|err| Error::ChipDeselect.with_source(DeviceError::Pin(err)));
+
+        self.release();
+        result
+    }
+
+    /// Split the transfer into chunks no longer than the bus allows.
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Source> {
+        self.with_bus(|locked| locked.raw_transfer(words))
+    }
+
+    /// Set the clock speed, which is applied before every transfer.
+    fn set_clock_speed(&mut self, speed: u32) -> Result<(), Self::Source> {
+        if !self.is_clock_speed() {
+            return Err(ErrorKind::ClockSpeed.into());
+        }
+
+        self.config.clock_speed = Some(speed);
+        Ok(())
+    }
+
+    /// Set the SPI mode, which is applied before every transfer.
+    fn set_spi_mode(&mut self, mode: Mode) -> Result<(), Self::Source> {
+        if !self.is_spi_mode() {
+            return Err(ErrorKind::SpiMode.into());
+        }
+
+        self.config.spi_mode = Some(mode);
+        Ok(())
+    }
+
+    /// Set the bit order, which is applied before every transfer.
+    fn set_bit_order(&mut self, order: BitOrder) -> Result<(), Self::Source> {
+        if !self.is_bit_order() {
+            return Err(ErrorKind::BitOrder.into());
+        }
+
+        self.config.bit_order = Some(order);
+        Ok(())
+    }
+
+    /// Set the word size, which is applied before every transfer.
+    fn set_word_size(&mut self, bits: u8) -> Result<(), Self::Source> {
+        if !self.is_word_size() {
+            return Err(ErrorKind::WordSize.into());
+        }
+
+        self.config.word_size = Some(bits);
+        Ok(())
+    }
+
+    fn delay_us(&mut self, us: u32) -> Result<(), Self::Source> {
+        self.with_bus(|locked| locked.delay_us(us))
+    }
+
+    /// Run the operations, holding the bus lock throughout.
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Self::Source> {
+        self.with_bus(|locked| locked.transaction(operations))
+    }
+}
+
+impl<M: BusMutex, CS: OutputPin> ChipSelect for Device<'_, M, CS> where M::Bus: SpiDev {}
+impl<M: BusMutex, CS: OutputPin> ClockSpeed for Device<'_, M, CS> where M::Bus: ClockSpeed {}
+impl<M: BusMutex, CS: OutputPin> SpiModeControl for Device<'_, M, CS> where M::Bus: SpiModeControl {}
+impl<M: BusMutex, CS: OutputPin> BitOrderControl for Device<'_, M, CS> where M::Bus: BitOrderControl {}
+impl<M: BusMutex, CS: OutputPin> WordSizeControl for Device<'_, M, CS> where M::Bus: WordSizeControl {}
+
+#[cfg(feature = "eh1")]
+impl<M: BusMutex, CS: OutputPin> _eh1::spi::ErrorType for Device<'_, M, CS>
+where
+    M::Bus: SpiDev,
+    <M::Bus as SpiDev>::Source: core::fmt::Debug,
+    CS::Error: core::fmt::Debug,
+{
+    type Error = Error<Source<M::Bus, CS>>;
+}
+
+#[cfg(feature = "eh1")]
+impl<M: BusMutex, CS: OutputPin> _eh1::spi::SpiDevice for Device<'_, M, CS>
+where
+    M::Bus: SpiDev,
+    <M::Bus as SpiDev>::Source: core::fmt::Debug,
+    CS::Error: core::fmt::Debug,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [_eh1::spi::Operation<'_, u8>],
+    ) -> Result<(), Source<M::Bus, CS>> {
+        self.with_bus(|locked| crate::transport::eh1::transaction(locked, operations))
+    }
+}
+
+/// A [`Device`] while it holds the bus lock.
+struct Locked<'b, B: SpiDev, CS: OutputPin> {
+    spi: &'b mut B,
+    cs: &'b mut CS,
+    polarity: Polarity,
+}
+
+impl<B: SpiDev, CS: OutputPin> Transfer<u8> for Locked<'_, B, CS> {
+    impl_cs_transfer_common!();
+}
+
+impl<B: SpiDev, CS: OutputPin> SpiDev for Locked<'_, B, CS> {
+    type Source = Source<B, CS>;
+
+    impl_cs_common!(DeviceError::Pin);
+
+    fn max_transfer_len(&self) -> Option<usize> {
+        self.spi.max_transfer_len()
+    }
+
+    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Source> {
+        let max = self.spi.max_transfer_len();
+        chunked(words, max, |chunk| {
+            self.spi
+                .transfer(chunk)
+                .map_err(|err| err.map_source(DeviceError::Spi))
+                .and(Ok(()))
+        })
+    }
+
+    fn delay_us(&mut self, us: u32) -> Result<(), Self::Source> {
+        self.spi
+            .delay_us(us)
+            .map_err(|err| err.map_source(DeviceError::Spi))
+    }
+}
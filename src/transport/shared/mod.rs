@@ -0,0 +1,123 @@
+mod build;
+mod device;
+mod mutex;
+
+pub use mutex::{BusMutex, RefCellBus};
+
+#[cfg(feature = "critical-section")]
+pub use mutex::CriticalSectionBus;
+
+#[cfg(feature = "std")]
+pub use mutex::MutexBus;
+
+use super::SpiDev;
+use core::sync::atomic::AtomicUsize;
+
+/// Owns an SPI bus which is shared by several devices, each with its own
+/// chip select pin, polarity and clock speed.
+///
+/// The bus is an [`SpiDev`] whose chip select is not controlled by a pin,
+/// such as a transport initialized without a chip select. The lock `M`
+/// determines how concurrent access is handled.
+///
+/// A device holds the bus from [`select`](SpiDev::select) until
+/// [`deselect`](SpiDev::deselect), and its settings are applied when it
+/// selects the chip. Until then, other devices cannot select their chips,
+/// transfer or reconfigure the bus: they fail with
+/// [`BusBusy`](crate::ErrorKind::BusBusy), or wait with a [`MutexBus`].
+///
+/// ```
+/// # #[cfg(feature = "dev")] {
+/// use rpio_utils::{*, dev::*};
+///
+/// let (sck, _) = Mock::pin("SCK").without_log().init();
+/// let (mosi, _) = Mock::pin("MOSI").without_log().init();
+/// let (miso, _) = Mock::input_pin("MISO").without_log().init();
+/// let (adc_cs, _) = Mock::pin("ADC").without_log().init();
+/// let (flash_cs, _) = Mock::pin("Flash").without_log().init();
+///
+/// let recorder = EventRecorder::new();
+/// let spi = Intercept::spi("Bus")
+///     .without_log()
+///     .with_subscriber(recorder.clone())
+///     .init(Transport::bitbang(sck, mosi, miso, NoDelay).init());
+///
+/// let bus = SharedBus::ref_cell(spi);
+/// let mut adc = bus.device(adc_cs).with_clock_speed(1_000_000).init();
+/// let mut flash = bus.device(flash_cs).with_clock_speed(50_000).init();
+///
+/// adc.select().unwrap();
+/// adc.raw_transfer(&mut [0x01]).unwrap();
+///
+/// // The flash cannot reconfigure the bus while the ADC is selected
+/// assert_eq!(flash.transfer(&mut [0x02]).unwrap_err(), ErrorKind::BusBusy);
+/// assert_eq!(flash.select().unwrap_err(), ErrorKind::BusBusy);
+///
+/// adc.raw_transfer(&mut [0x03]).unwrap();
+/// adc.deselect().unwrap();
+/// flash.transfer(&mut [0x04]).unwrap();
+///
+/// let operations: Vec<_> = recorder.events().iter().map(|event| event.operation).collect();
+/// assert_eq!(
+///     operations,
+///     [
+///         SpiOperation::SetClockSpeed(1_000_000),
+///         SpiOperation::Transfer,
+///         SpiOperation::Transfer,
+///         SpiOperation::SetClockSpeed(50_000),
+///         SpiOperation::Transfer,
+///     ]
+/// );
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SharedBus<M: BusMutex> {
+    bus: M,
+    /// The id of the device holding the bus, or [`device::FREE`].
+    owner: AtomicUsize,
+    /// The id of the last device added.
+    devices: AtomicUsize,
+}
+
+impl<M: BusMutex> SharedBus<M>
+where
+    M::Bus: SpiDev,
+{
+    /// Share the bus, guarded by the lock `M`.
+    pub fn new(bus: M::Bus) -> Self {
+        Self {
+            bus: M::create(bus),
+            owner: AtomicUsize::new(device::FREE),
+            devices: AtomicUsize::new(device::FREE),
+        }
+    }
+
+    /// Release the bus. All device handles must have been dropped.
+    pub fn into_inner(self) -> M::Bus {
+        self.bus.into_inner()
+    }
+}
+
+impl<B: SpiDev> SharedBus<RefCellBus<B>> {
+    /// Share the bus between devices on a single thread.
+    pub fn ref_cell(bus: B) -> Self {
+        Self::new(bus)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<B: SpiDev> SharedBus<CriticalSectionBus<B>> {
+    /// Share the bus, locking it inside a critical section.
+    pub fn critical_section(bus: B) -> Self {
+        Self::new(bus)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<B: SpiDev> SharedBus<MutexBus<B>> {
+    /// Share the bus between threads, locking it with a
+    /// [`std::sync::Mutex`].
+    pub fn mutex(bus: B) -> Self {
+        Self::new(bus)
+    }
+}
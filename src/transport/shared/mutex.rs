@@ -0,0 +1,117 @@
+use core::cell::RefCell;
+
+/// A lock guarding a [`SharedBus`](super::SharedBus).
+pub trait BusMutex {
+    /// The guarded bus.
+    type Bus;
+
+    /// Guard the bus.
+    fn create(bus: Self::Bus) -> Self;
+
+    /// Run `f` with exclusive access to the bus.
+    fn lock<R>(&self, f: impl FnOnce(&mut Self::Bus) -> R) -> R;
+
+    /// Run `f` with exclusive access to the bus once `ready` returns true.
+    /// By default the lock does not wait, and returns `None` if the bus is
+    /// not ready.
+    fn lock_when<R>(
+        &self,
+        mut ready: impl FnMut() -> bool,
+        f: impl FnOnce(&mut Self::Bus) -> R,
+    ) -> Option<R> {
+        self.lock(|bus| if ready() { Some(f(bus)) } else { None })
+    }
+
+    /// Wake any thread waiting in [`lock_when`](BusMutex::lock_when), as
+    /// the bus may have become ready.
+    fn notify(&self) {}
+
+    /// Release the bus.
+    fn into_inner(self) -> Self::Bus;
+}
+
+/// Guards the bus with a [`RefCell`]. Devices must be used from a single
+/// thread, and a device must not be used while another holds the lock.
+#[derive(Debug)]
+pub struct RefCellBus<B>(RefCell<B>);
+
+impl<B> BusMutex for RefCellBus<B> {
+    type Bus = B;
+
+    fn create(bus: B) -> Self {
+        Self(RefCell::new(bus))
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut B) -> R) -> R {
+        f(&mut self.0.borrow_mut())
+    }
+
+    fn into_inner(self) -> B {
+        self.0.into_inner()
+    }
+}
+
+/// Guards the bus with a [`critical_section::Mutex`].
+#[cfg(feature = "critical-section")]
+#[derive(Debug)]
+pub struct CriticalSectionBus<B>(critical_section::Mutex<RefCell<B>>);
+
+#[cfg(feature = "critical-section")]
+impl<B> BusMutex for CriticalSectionBus<B> {
+    type Bus = B;
+
+    fn create(bus: B) -> Self {
+        Self(critical_section::Mutex::new(RefCell::new(bus)))
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut B) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.0.borrow_ref_mut(cs)))
+    }
+
+    fn into_inner(self) -> B {
+        self.0.into_inner().into_inner()
+    }
+}
+
+/// Guards the bus with a [`std::sync::Mutex`]. A poisoned lock is
+/// recovered, since the bus holds no state that a panic could corrupt.
+///
+/// While a device has its chip selected, devices on other threads wait for
+/// it to deselect instead of failing. Waiting on the same thread never ends.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MutexBus<B>(std::sync::Mutex<B>, std::sync::Condvar);
+
+#[cfg(feature = "std")]
+impl<B> BusMutex for MutexBus<B> {
+    type Bus = B;
+
+    fn create(bus: B) -> Self {
+        Self(std::sync::Mutex::new(bus), std::sync::Condvar::new())
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut B) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    fn lock_when<R>(
+        &self,
+        mut ready: impl FnMut() -> bool,
+        f: impl FnOnce(&mut B) -> R,
+    ) -> Option<R> {
+        let guard = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let mut guard = self
+            .1
+            .wait_while(guard, |_| !ready())
+            .unwrap_or_else(|err| err.into_inner());
+        Some(f(&mut guard))
+    }
+
+    fn notify(&self) {
+        self.1.notify_all();
+    }
+
+    fn into_inner(self) -> B {
+        self.0.into_inner().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,93 @@
+use super::{ErrorKind, Result};
+use embedded_hal::blocking::delay::DelayUs;
+
+/// Chip select timing requirements, in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// Minimum time from selecting the chip to the first clock edge.
+    pub setup_us: u32,
+    /// Minimum time from the last clock edge to deselecting the chip.
+    pub hold_us: u32,
+    /// Minimum time the chip remains deselected between frames.
+    pub idle_us: u32,
+}
+
+impl Timing {
+    /// Create a timing profile.
+    pub const fn new(setup_us: u32, hold_us: u32, idle_us: u32) -> Self {
+        Self {
+            setup_us,
+            hold_us,
+            idle_us,
+        }
+    }
+}
+
+/// A placeholder [`DelayUs`] for transports constructed without a delay.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoDelay;
+
+impl DelayUs<u32> for NoDelay {
+    fn delay_us(&mut self, _us: u32) {}
+}
+
+/// Waits around chip select changes to satisfy a [`Timing`].
+#[derive(Debug)]
+pub struct Timer<D: DelayUs<u32>> {
+    delay: Option<D>,
+    timing: Timing,
+    idle_pending: bool,
+}
+
+impl<D: DelayUs<u32>> Timer<D> {
+    pub fn new(delay: Option<D>, timing: Timing) -> Self {
+        Self {
+            delay,
+            timing,
+            idle_pending: false,
+        }
+    }
+
+    /// Whether a delay was provided.
+    pub fn is_delay(&self) -> bool {
+        self.delay.is_some()
+    }
+
+    /// Wait for the given number of microseconds.
+    pub fn delay_us<E>(&mut self, us: u32) -> Result<(), E> {
+        match &mut self.delay {
+            Some(delay) => {
+                delay.delay_us(us);
+                Ok(())
+            }
+            None => Err(ErrorKind::NotImplemented.into()),
+        }
+    }
+
+    /// Select the chip with `set`, waiting out the idle time since the last
+    /// deselect and then the setup time.
+    pub fn select<E>(&mut self, set: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+        if self.idle_pending {
+            self.wait(self.timing.idle_us);
+        }
+
+        set()?;
+        self.idle_pending = false;
+        self.wait(self.timing.setup_us);
+        Ok(())
+    }
+
+    /// Deselect the chip with `set` after waiting out the hold time.
+    pub fn deselect<E>(&mut self, set: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+        self.wait(self.timing.hold_us);
+        set()?;
+        self.idle_pending = true;
+        Ok(())
+    }
+
+    fn wait(&mut self, us: u32) {
+        if let (Some(delay), true) = (&mut self.delay, us > 0) {
+            delay.delay_us(us);
+        }
+    }
+}
@@ -1,13 +1,17 @@
-use super::{Error, Result};
-use crate::Transfer;
+use super::{resume, BitOrder, Error, ErrorKind, Operation, Result, Resume, SelectGuard};
+use crate::{Mode, Transfer};
 
 /// Indicates that the implementation of [`Transfer<u8>`](Transfer) for this
 /// struct:
 ///
 /// - Selects the chip at the start of transfer.
 /// - Deselects the chip at the end of successful transfer.
-/// - Uses the [`Error`] type.
-pub trait SpiDev: Transfer<u8, Error = Error> {
+/// - Uses the [`Error`] type, with the error of the underlying bus or pins
+///   as its [`Source`](Self::Source).
+pub trait SpiDev: Transfer<u8, Error = Error<<Self as SpiDev>::Source>> {
+    /// The error of the underlying bus or pins, kept in each [`Error`].
+    type Source;
+
     /// Whether chip selection can be controlled
     fn is_chip_select(&self) -> bool {
         false
@@ -18,42 +22,234 @@ pub trait SpiDev: Transfer<u8, Error = Error> {
         false
     }
 
+    /// Whether the SPI mode can be controlled
+    fn is_spi_mode(&self) -> bool {
+        false
+    }
+
+    /// Whether bit order can be controlled
+    fn is_bit_order(&self) -> bool {
+        false
+    }
+
+    /// Whether the number of bits per word can be controlled
+    fn is_word_size(&self) -> bool {
+        false
+    }
+
+    /// The most bytes exchanged with the device at once, if limited.
+    /// Transports which control chip selection split longer transfers into
+    /// chunks while the chip stays selected.
+    fn max_transfer_len(&self) -> Option<usize> {
+        None
+    }
+
     /// Select the chip.
     ///
     /// This typically drives the pin low, but in some configurations could
     /// drive the pin high.
-    fn select(&mut self) -> Result {
-        Err(Error::NotImplemented)
+    fn select(&mut self) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
     }
 
     /// Deselect the chip.
     ///
     /// This typically drives the pin high, but in some configurations could
     /// drive the pin low.
-    fn deselect(&mut self) -> Result {
-        Err(Error::NotImplemented)
+    fn deselect(&mut self) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
     }
 
     /// Exchange bytes with the chip without selecting or deslecting it.
-    fn raw_transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
-        Err(Error::NotImplemented)
+    fn raw_transfer<'w>(&mut self, _words: &'w mut [u8]) -> Result<&'w [u8], Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
     }
 
     /// Exchange bytes with the chip without selecting it. Deselect only if an
     /// error occurs during the transfer.
-    fn raw_transfer_or_deselect<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8]> {
+    fn raw_transfer_or_deselect<'w>(
+        &mut self,
+        words: &'w mut [u8],
+    ) -> Result<&'w [u8], Self::Source> {
         self.raw_transfer(words)
-            .map_err(|err| self.deselect().map_or(Error::ChipDeselect, |_| err))
+            .map_err(|err| err.after_deselect(self.deselect()))
     }
 
     /// Set the SPI clock speed.
-    fn set_clock_speed(&mut self, speed: u32) -> Result {
-        Err(Error::NotImplemented)
+    fn set_clock_speed(&mut self, _speed: u32) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Set the SPI mode (clock polarity and phase).
+    fn set_spi_mode(&mut self, _mode: Mode) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Set the order in which the bits of each byte are clocked.
+    fn set_bit_order(&mut self, _order: BitOrder) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Set the number of bits per word. Words wider than 8 bits span
+    /// several bytes of each transfer, laid out as the bus expects.
+    fn set_word_size(&mut self, _bits: u8) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Wait for the given number of microseconds.
+    fn delay_us(&mut self, _us: u32) -> Result<(), Self::Source> {
+        Err(ErrorKind::NotImplemented.into())
+    }
+
+    /// Run the operations in order while the chip is selected. If an error
+    /// occurs, the chip is deselected and the remaining operations are
+    /// skipped. Errors count the bytes exchanged by the whole transaction.
+    ///
+    /// When chip selection cannot be controlled, each operation is a
+    /// separate [`transfer`](Transfer::transfer) unless the transport groups
+    /// them into one, as the hal, rp2040 and rppal transports do. Without
+    /// the `std` feature, writes longer than 32 bytes then return
+    /// [`NotImplemented`](ErrorKind::NotImplemented), since they would be
+    /// split over several transfers.
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Self::Source> {
+        let mut done = 0;
+
+        if !self.is_chip_select() {
+            return operations.iter_mut().try_for_each(|operation| {
+                operation
+                    .run(self, |spi, words| spi.transfer(words).and(Ok(())))
+                    .map_err(|err| err.offset(done))?;
+                done += operation.len();
+                Ok(())
+            });
+        }
+
+        self.select()?;
+
+        for operation in operations.iter_mut() {
+            match operation {
+                Operation::DelayUs(us) => self
+                    .delay_us(*us)
+                    .map_err(|err| err.offset(done).after_deselect(self.deselect()))?,
+                _ => operation
+                    .run(self, |spi, words| {
+                        spi.raw_transfer_or_deselect(words).and(Ok(()))
+                    })
+                    .map_err(|err| err.offset(done))?,
+            }
+
+            done += operation.len();
+        }
+
+        self.deselect()
+    }
+
+    /// Exchange bytes in chunks, each in its own selection of the chip, and
+    /// resume a chunk which fails from the byte it failed at. Transfers which
+    /// do not report their progress resume from the start of the chunk.
+    ///
+    /// `begin` is called with the offset of the next byte, after the chip is
+    /// selected and before the bytes are exchanged, to send anything the chip
+    /// expects first, such as a read command and the address of that byte.
+    /// Failures which leave the chip selected are not resumed.
+    ///
+    /// `begin` and the chunk must share a selection of the chip, so
+    /// transports which do not control chip selection return
+    /// [`NotImplemented`](ErrorKind::NotImplemented).
+    ///
+    /// ```
+    /// # #[cfg(feature = "dev")] {
+    /// use rpio_utils::{*, dev::*};
+    ///
+    /// let (spi, spi_control) = Mock::spi("MockSPI").without_log().init();
+    /// let (cs, _) = Mock::pin("MockCS").without_log().init();
+    /// let mut spi = Transport::hal(spi).with_cs(cs).init();
+    ///
+    /// let mut data = [0u8; 256];
+    /// spi.transfer_resumable(&mut data, Resume::new(64, 3), |spi, offset| {
+    ///     let [_, a2, a1, a0] = (0x1000 + offset as u32).to_be_bytes();
+    ///     spi.raw_transfer(&mut [0x03, a2, a1, a0]).and(Ok(()))
+    /// })
+    /// .unwrap();
+    ///
+    /// let traffic = spi_control.get_traffic();
+    /// assert_eq!(traffic.len(), 8);
+    /// assert_eq!(traffic[2].0, [0x03, 0x00, 0x10, 0x40]);
+    /// assert_eq!(traffic[3].0.len(), 64);
+    ///
+    /// let (spi, _) = Mock::spi("MockSPI").without_log().init();
+    /// let mut spi = Transport::hal(spi).init();
+    /// let err = spi
+    ///     .transfer_resumable(&mut data, Resume::new(64, 3), |_, _| Ok(()))
+    ///     .unwrap_err();
+    /// assert_eq!(err, ErrorKind::NotImplemented);
+    /// # }
+    /// ```
+    fn transfer_resumable<F>(
+        &mut self,
+        words: &mut [u8],
+        resume: Resume,
+        mut begin: F,
+    ) -> Result<(), Self::Source>
+    where
+        Self: Sized,
+        F: FnMut(&mut Self, usize) -> Result<(), Self::Source>,
+    {
+        if !self.is_chip_select() {
+            return Err(ErrorKind::NotImplemented.into());
+        }
+
+        let chunk_len = match resume.chunk_len {
+            0 => words.len(),
+            chunk_len => chunk_len,
+        };
+
+        let mut offset = 0;
+        let mut failures = 0;
+
+        while offset < words.len() {
+            let end = words.len().min(offset + chunk_len);
+
+            match resume::chunk(self, offset, &mut words[offset..end], &mut begin) {
+                Ok(_) => {
+                    offset = end;
+                    failures = 0;
+                }
+                Err(err) => {
+                    failures += 1;
+                    let selected = err.deselect_failed() || err == ErrorKind::ChipDeselect;
+
+                    if selected || failures > resume.retries {
+                        return Err(err.offset(offset));
+                    }
+
+                    offset += err.completed().unwrap_or(0);
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
 /// Indicates that chip selection is controlled by a user-defined output pin.
-pub trait ChipSelect: SpiDev {}
+pub trait ChipSelect: SpiDev {
+    /// Select the chip until the returned guard is dropped or
+    /// [finished](SelectGuard::finish).
+    fn selected(&mut self) -> Result<SelectGuard<'_, Self>, Self::Source> {
+        SelectGuard::new(self)
+    }
+}
 
 /// Indicates that the SPI clock speed can be set during operation.
 pub trait ClockSpeed: SpiDev {}
+
+/// Indicates that the SPI mode can be set during operation.
+pub trait SpiModeControl: SpiDev {}
+
+/// Indicates that the bit order can be set during operation. The order may
+/// be emulated by reversing bits in software.
+pub trait BitOrderControl: SpiDev {}
+
+/// Indicates that the number of bits per word can be set during operation.
+pub trait WordSizeControl: SpiDev {}
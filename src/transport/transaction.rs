@@ -0,0 +1,165 @@
+use super::{Result, SpiDev};
+
+/// Size of the stack buffer used to clock out [`Operation::Write`] bytes
+/// without the `std` feature.
+#[cfg(not(feature = "std"))]
+pub(crate) const SCRATCH_LEN: usize = 32;
+
+/// A single step of an [`SpiDev::transaction`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Operation<'a> {
+    /// Write the bytes, discarding the bytes received.
+    Write(&'a [u8]),
+    /// Read into the buffer, writing `0x00` bytes.
+    Read(&'a mut [u8]),
+    /// Exchange the bytes in place.
+    Transfer(&'a mut [u8]),
+    /// Wait for the given number of microseconds.
+    DelayUs(u32),
+}
+
+impl Operation<'_> {
+    /// The number of bytes the operation exchanges.
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Operation::Write(words) => words.len(),
+            Operation::Read(words) | Operation::Transfer(words) => words.len(),
+            Operation::DelayUs(_) => 0,
+        }
+    }
+
+    /// Run the operation, clocking any bytes through `exchange`.
+    pub(crate) fn run<S: SpiDev + ?Sized>(
+        &mut self,
+        spi: &mut S,
+        exchange: fn(&mut S, &mut [u8]) -> Result<(), S::Source>,
+    ) -> Result<(), S::Source> {
+        match self {
+            Operation::Write(words) => {
+                let selected = spi.is_chip_select();
+                copied(
+                    words,
+                    words.len(),
+                    selected,
+                    |words| exchange(spi, words),
+                    |_, _| (),
+                )
+            }
+            Operation::Read(words) => {
+                words.fill(0x00);
+                exchange(spi, words)
+            }
+            Operation::Transfer(words) => exchange(spi, words),
+            Operation::DelayUs(us) => spi.delay_us(*us),
+        }
+    }
+}
+
+/// Exchange `len` bytes starting with a copy of `write`, padded with `0x00`,
+/// passing the bytes received to `read` with their offset.
+///
+/// With the `std` feature the bytes are exchanged at once. Otherwise they
+/// are clocked through a stack buffer, which splits them into several
+/// exchanges. That is only possible while the chip stays `selected`, so
+/// longer writes fail with [`NotImplemented`](super::ErrorKind::NotImplemented)
+/// when each exchange is a transfer of its own.
+pub(crate) fn copied<E>(
+    write: &[u8],
+    len: usize,
+    selected: bool,
+    mut exchange: impl FnMut(&mut [u8]) -> Result<(), E>,
+    mut read: impl FnMut(usize, &[u8]),
+) -> Result<(), E> {
+    #[cfg(feature = "std")]
+    {
+        let _ = selected;
+        let mut words = std::vec![0x00; len];
+        let tx_len = write.len().min(len);
+        words[..tx_len].copy_from_slice(&write[..tx_len]);
+        exchange(&mut words)?;
+        read(0, &words);
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        if !selected && len > SCRATCH_LEN {
+            return Err(super::ErrorKind::NotImplemented.into());
+        }
+
+        let mut scratch = [0u8; SCRATCH_LEN];
+
+        for start in (0..len).step_by(SCRATCH_LEN) {
+            let scratch = &mut scratch[..SCRATCH_LEN.min(len - start)];
+            scratch.fill(0x00);
+
+            let tx = write.get(start..).unwrap_or_default();
+            let tx_len = tx.len().min(scratch.len());
+            scratch[..tx_len].copy_from_slice(&tx[..tx_len]);
+
+            exchange(scratch).map_err(|err| err.offset(start))?;
+            read(start, scratch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Join the bytes of the operations into one exchange, for transports which
+/// select the chip for each transfer, and split the bytes received back.
+///
+/// Delays cannot be part of the exchange, so they fail with
+/// [`NotImplemented`](super::ErrorKind::NotImplemented), as do transactions
+/// longer than the stack buffer without the `std` feature.
+#[cfg(any(feature = "hal", feature = "rp2040"))]
+pub(crate) fn grouped<E>(
+    operations: &mut [Operation<'_>],
+    exchange: impl FnOnce(&mut [u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    if operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::DelayUs(_)))
+    {
+        return Err(super::ErrorKind::NotImplemented.into());
+    }
+
+    let len = operations.iter().map(Operation::len).sum();
+
+    if len == 0 {
+        return Ok(());
+    }
+
+    #[cfg(feature = "std")]
+    let mut buffer = std::vec![0x00; len];
+
+    #[cfg(not(feature = "std"))]
+    let mut scratch = [0u8; SCRATCH_LEN];
+    #[cfg(not(feature = "std"))]
+    let buffer = match scratch.get_mut(..len) {
+        Some(buffer) => buffer,
+        None => return Err(super::ErrorKind::NotImplemented.into()),
+    };
+
+    let mut start = 0;
+    for operation in operations.iter() {
+        let words = &mut buffer[start..start + operation.len()];
+        match operation {
+            Operation::Write(tx) => words.copy_from_slice(tx),
+            Operation::Transfer(tx) => words.copy_from_slice(tx),
+            Operation::Read(_) | Operation::DelayUs(_) => (),
+        }
+        start += words.len();
+    }
+
+    exchange(&mut buffer[..])?;
+
+    let mut start = 0;
+    for operation in operations.iter_mut() {
+        let len = operation.len();
+        if let Operation::Read(rx) | Operation::Transfer(rx) = operation {
+            rx.copy_from_slice(&buffer[start..start + len]);
+        }
+        start += len;
+    }
+
+    Ok(())
+}